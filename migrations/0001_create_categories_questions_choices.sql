@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS categories(
+    name VARCHAR(256) PRIMARY KEY,
+    active BOOL NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS questions(
+    id BIGSERIAL PRIMARY KEY,
+    text TEXT NOT NULL,
+    category VARCHAR(256) NOT NULL REFERENCES categories(name) ON DELETE cascade
+);
+
+CREATE TABLE IF NOT EXISTS choices(
+    id BIGSERIAL PRIMARY KEY,
+    text TEXT NOT NULL,
+    correct BOOL NOT NULL DEFAULT false,
+    question_id BIGSERIAL NOT NULL REFERENCES questions(id) ON DELETE cascade
+);
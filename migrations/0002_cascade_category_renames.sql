@@ -0,0 +1,4 @@
+ALTER TABLE questions
+    DROP CONSTRAINT IF EXISTS questions_category_fkey,
+    ADD CONSTRAINT questions_category_fkey FOREIGN KEY (category)
+        REFERENCES categories(name) ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS questions_category_idx ON questions(category);
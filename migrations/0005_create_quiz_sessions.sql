@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS quiz_sessions(
+    id BIGSERIAL PRIMARY KEY,
+    category VARCHAR(256) NOT NULL REFERENCES categories(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS quiz_session_answers(
+    session_id BIGINT NOT NULL REFERENCES quiz_sessions(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    question_id BIGINT NOT NULL REFERENCES questions(id) ON DELETE CASCADE,
+    choice_id BIGINT,
+    correct BOOL,
+    answered_at TIMESTAMPTZ,
+    time_taken_ms BIGINT,
+    PRIMARY KEY (session_id, position),
+    UNIQUE (session_id, question_id)
+);
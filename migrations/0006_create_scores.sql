@@ -0,0 +1,12 @@
+ALTER TABLE quiz_sessions ADD COLUMN IF NOT EXISTS player VARCHAR(64);
+
+CREATE TABLE IF NOT EXISTS scores(
+    session_id BIGINT PRIMARY KEY REFERENCES quiz_sessions(id) ON DELETE CASCADE,
+    player VARCHAR(64) NOT NULL,
+    category VARCHAR(256) NOT NULL REFERENCES categories(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    score BIGINT NOT NULL,
+    total BIGINT NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS scores_category_completed_at_idx ON scores(category, completed_at);
@@ -0,0 +1,4 @@
+ALTER TABLE questions
+    ADD COLUMN IF NOT EXISTS difficulty SMALLINT NOT NULL DEFAULT 2 CHECK (difficulty BETWEEN 1 AND 3);
+
+CREATE INDEX IF NOT EXISTS questions_category_difficulty_idx ON questions(category, difficulty);
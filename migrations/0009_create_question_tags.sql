@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS question_tags(
+    question_id BIGINT NOT NULL REFERENCES questions(id) ON DELETE cascade,
+    tag VARCHAR(64) NOT NULL,
+    PRIMARY KEY (question_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS question_tags_tag_idx ON question_tags(tag);
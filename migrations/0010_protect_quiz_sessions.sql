@@ -0,0 +1,9 @@
+ALTER TABLE quiz_sessions ADD COLUMN IF NOT EXISTS token_hash VARCHAR(64);
+
+-- Questions of quiz sessions can not be deleted, so that sessions and their scores never change.
+-- NO ACTION behaves like RESTRICT, but is checked at the end of the statement: deleting a
+-- category still deletes its questions along with its sessions.
+ALTER TABLE quiz_session_answers
+    DROP CONSTRAINT IF EXISTS quiz_session_answers_question_id_fkey,
+    ADD CONSTRAINT quiz_session_answers_question_id_fkey
+        FOREIGN KEY (question_id) REFERENCES questions(id) ON DELETE NO ACTION;
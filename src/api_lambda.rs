@@ -0,0 +1,19 @@
+extern crate lambda_runtime as lambda;
+extern crate log;
+extern crate quizzical;
+extern crate simple_logger;
+
+use lambda::{start, Context};
+use quizzical::apigateway::*;
+use quizzical::events::LambdaRequest;
+use quizzical::handlers::api_router;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_level(log::Level::Debug).unwrap();
+    start(
+        |request: LambdaRequest, c: Context| router_lambda_adapter(request, c, &api_router()),
+        None,
+    );
+    Ok(())
+}
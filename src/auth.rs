@@ -0,0 +1,695 @@
+use apigateway::{APIErrorResponse, APIErrorType, APIGatewayEvent};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use ring::{hmac, signature};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::from_slice;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// Clock skew tolerated when checking `exp` and `nbf`, unless `JWT_LEEWAY_SECONDS` is set.
+pub const DEFAULT_LEEWAY_SECONDS: i64 = 60;
+
+/* #region Permission */
+
+/// What a caller may do. Handlers that modify content declare the permission they require.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Create, update and delete questions, and read them with their correct choices.
+    QuestionsWrite,
+    /// Create, rename, delete, activate and list all categories.
+    CategoriesAdmin,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Permission::QuestionsWrite => write!(f, "questions:write"),
+            Permission::CategoriesAdmin => write!(f, "categories:admin"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> Result<Permission, AuthError> {
+        match s {
+            "questions:write" => Ok(Permission::QuestionsWrite),
+            "categories:admin" => Ok(Permission::CategoriesAdmin),
+            _ => Err(AuthError::Configuration(format!(
+                "Unknown permission '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/* #region AuthError */
+
+#[derive(Debug, PartialEq)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken(String),
+    Forbidden(Permission),
+    Configuration(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthError::MissingToken => write!(f, "A bearer token is required"),
+            AuthError::InvalidToken(reason) => write!(f, "Invalid token: {}", reason),
+            AuthError::Forbidden(permission) => {
+                write!(f, "The '{}' permission is required", permission)
+            }
+            AuthError::Configuration(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::convert::From<AuthError> for APIErrorResponse {
+    fn from(error: AuthError) -> Self {
+        let detail = Some(format!("{}", error));
+        match error {
+            AuthError::MissingToken | AuthError::InvalidToken(_) => {
+                APIErrorType::Unauthorized { detail }.into()
+            }
+            AuthError::Forbidden(_) => APIErrorType::Forbidden { detail }.into(),
+            AuthError::Configuration(_) => APIErrorResponse::error(
+                500,
+                "auth.configuration".into(),
+                "Authentication Misconfigured".into(),
+                detail,
+                None,
+            ),
+        }
+    }
+}
+
+fn invalid<S: Into<String>>(reason: S) -> AuthError {
+    AuthError::InvalidToken(reason.into())
+}
+
+/* #region Keys */
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyMaterial {
+    /// HS256 shared secret.
+    Hmac(Vec<u8>),
+    /// RS256 public key as big-endian modulus and exponent.
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub kid: Option<String>,
+    pub material: KeyMaterial,
+}
+
+impl Key {
+    fn algorithm(&self) -> &'static str {
+        match self.material {
+            KeyMaterial::Hmac(_) => "HS256",
+            KeyMaterial::Rsa { .. } => "RS256",
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self.material {
+            KeyMaterial::Hmac(ref secret) => {
+                let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+                hmac::verify(&key, message, signature).is_ok()
+            }
+            KeyMaterial::Rsa { ref n, ref e } => signature::RsaPublicKeyComponents { n, e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    key_use: Option<String>,
+    k: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+/// Reads the `RSA` and `oct` signing keys of a JSON Web Key Set. Other keys are ignored.
+pub fn parse_jwks(json: &[u8]) -> Result<Vec<Key>, AuthError> {
+    let jwks: Jwks =
+        from_slice(json).map_err(|e| AuthError::Configuration(format!("Invalid JWKS: {}", e)))?;
+
+    let decode = |value: Option<String>, kid: &Option<String>| {
+        value
+            .and_then(|value| URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok())
+            .ok_or_else(|| AuthError::Configuration(format!("Invalid JWKS key {:?}", kid)))
+    };
+
+    let mut keys = vec![];
+    for jwk in jwks.keys {
+        if jwk.key_use.as_ref().is_some_and(|key_use| key_use != "sig") {
+            continue;
+        }
+        let material = match jwk.kty.as_str() {
+            "oct" => KeyMaterial::Hmac(decode(jwk.k, &jwk.kid)?),
+            "RSA" => KeyMaterial::Rsa {
+                n: decode(jwk.n, &jwk.kid)?,
+                e: decode(jwk.e, &jwk.kid)?,
+            },
+            _ => continue,
+        };
+        keys.push(Key {
+            kid: jwk.kid,
+            material,
+        });
+    }
+    Ok(keys)
+}
+
+/* #region Claims */
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(one) => one == audience,
+            Audience::Many(many) => many.iter().any(|one| one == audience),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: Option<String>,
+    pub iss: Option<String>,
+    pub aud: Option<Audience>,
+    pub exp: Option<i64>,
+    pub nbf: Option<i64>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+/* #region AuthConfig */
+
+/**
+ * Validates bearer tokens and maps their `roles` claim to permissions.
+ *
+ * When read from the environment (see `AuthConfig::from_env`), the keys come from
+ * `JWT_HS256_SECRET` and/or the JSON Web Key Set at `JWT_JWKS_FILE`. Tokens must be issued
+ * by `JWT_ISSUER` for `JWT_AUDIENCE`.
+ */
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub leeway_seconds: i64,
+    pub keys: Vec<Key>,
+    pub roles: HashMap<String, Vec<Permission>>,
+}
+
+impl AuthConfig {
+    pub fn new(issuer: &str, audience: &str, keys: Vec<Key>) -> AuthConfig {
+        AuthConfig {
+            issuer: issuer.into(),
+            audience: audience.into(),
+            leeway_seconds: DEFAULT_LEEWAY_SECONDS,
+            keys,
+            roles: default_roles(),
+        }
+    }
+
+    /**
+     * Returns `None` if neither `JWT_HS256_SECRET` nor `JWT_JWKS_FILE` is set.
+     *
+     * `JWT_ROLES` overrides the default roles, e.g.
+     * `editor=questions:write;admin=questions:write,categories:admin`.
+     */
+    pub fn from_env() -> Result<Option<AuthConfig>, AuthError> {
+        let mut keys = vec![];
+        if let Ok(secret) = env::var("JWT_HS256_SECRET") {
+            keys.push(Key {
+                kid: None,
+                material: KeyMaterial::Hmac(secret.into_bytes()),
+            });
+        }
+        if let Ok(path) = env::var("JWT_JWKS_FILE") {
+            let json = fs::read(&path).map_err(|e| {
+                AuthError::Configuration(format!("Failed to read JWKS file '{}': {}", path, e))
+            })?;
+            keys.extend(parse_jwks(&json)?);
+        }
+        if keys.is_empty() {
+            return Ok(None);
+        }
+
+        let required = |name: &str| {
+            env::var(name).map_err(|_| AuthError::Configuration(format!("{} required", name)))
+        };
+        let mut config =
+            AuthConfig::new(&required("JWT_ISSUER")?, &required("JWT_AUDIENCE")?, keys);
+
+        if let Ok(leeway) = env::var("JWT_LEEWAY_SECONDS") {
+            config.leeway_seconds = leeway.parse().map_err(|_| {
+                AuthError::Configuration("JWT_LEEWAY_SECONDS must be a number".into())
+            })?;
+        }
+        if let Ok(roles) = env::var("JWT_ROLES") {
+            config.roles = parse_roles(&roles)?;
+        }
+        Ok(Some(config))
+    }
+
+    /// Checks the signature, issuer, audience, expiry and not-before time of `token`.
+    pub fn validate(&self, token: &str, now: i64) -> Result<Claims, AuthError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        let (header, payload, signature) = match parts.as_slice() {
+            [header, payload, signature] => (header, payload, signature),
+            _ => return Err(invalid("expected three segments")),
+        };
+
+        let decode = |segment: &str| {
+            URL_SAFE_NO_PAD
+                .decode(segment)
+                .map_err(|_| invalid("malformed base64url"))
+        };
+        let header: Header =
+            from_slice(&decode(header)?).map_err(|_| invalid("malformed header"))?;
+
+        // Only the algorithm of the key decides how the signature is checked, so an RS256 public
+        // key can never be used as an HS256 secret.
+        let signed = &token[..token.rfind('.').unwrap_or(0)];
+        let signature = decode(signature)?;
+        let verified = self
+            .keys
+            .iter()
+            .filter(|key| key.algorithm() == header.alg)
+            .filter(|key| header.kid.is_none() || key.kid.is_none() || key.kid == header.kid)
+            .any(|key| key.verify(signed.as_bytes(), &signature));
+        if !verified {
+            return Err(invalid("signature verification failed"));
+        }
+
+        let claims: Claims =
+            from_slice(&decode(payload)?).map_err(|_| invalid("malformed claims"))?;
+
+        if claims.iss.as_ref() != Some(&self.issuer) {
+            return Err(invalid("unexpected issuer"));
+        }
+        if !claims
+            .aud
+            .as_ref()
+            .is_some_and(|aud| aud.contains(&self.audience))
+        {
+            return Err(invalid("unexpected audience"));
+        }
+        match claims.exp {
+            Some(exp) if now <= exp + self.leeway_seconds => {}
+            Some(_) => return Err(invalid("token expired")),
+            None => return Err(invalid("missing expiry")),
+        }
+        if claims
+            .nbf
+            .is_some_and(|nbf| now + self.leeway_seconds < nbf)
+        {
+            return Err(invalid("token not yet valid"));
+        }
+        Ok(claims)
+    }
+
+    pub fn permissions(&self, claims: &Claims) -> HashSet<Permission> {
+        claims
+            .roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// The subject of the request's bearer token, or `None` if the request has no token.
+    pub fn authenticate(&self, event: &APIGatewayEvent) -> Result<Option<String>, AuthError> {
+        match bearer_token(event) {
+            Some(token) => Ok(self.validate(token, Utc::now().timestamp())?.sub),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the claims of the bearer token in the `Authorization` header if it grants `permission`.
+    pub fn authorize(
+        &self,
+        event: &APIGatewayEvent,
+        permission: Permission,
+    ) -> Result<Claims, AuthError> {
+        let token = bearer_token(event).ok_or(AuthError::MissingToken)?;
+        let claims = self.validate(token, Utc::now().timestamp())?;
+
+        match self.permissions(&claims).contains(&permission) {
+            true => Ok(claims),
+            false => Err(AuthError::Forbidden(permission)),
+        }
+    }
+}
+
+/// `admin` may do everything, `editor` may only manage questions.
+pub fn default_roles() -> HashMap<String, Vec<Permission>> {
+    let mut roles = HashMap::new();
+    roles.insert(
+        "admin".to_string(),
+        vec![Permission::QuestionsWrite, Permission::CategoriesAdmin],
+    );
+    roles.insert("editor".to_string(), vec![Permission::QuestionsWrite]);
+    roles
+}
+
+fn parse_roles(config: &str) -> Result<HashMap<String, Vec<Permission>>, AuthError> {
+    let mut roles = HashMap::new();
+    for entry in config.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.splitn(2, '=');
+        let role = parts.next().unwrap_or("").trim();
+        let permissions = parts
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(Permission::from_str)
+            .collect::<Result<Vec<Permission>, AuthError>>()?;
+        roles.insert(role.to_string(), permissions);
+    }
+    Ok(roles)
+}
+
+fn bearer_token(event: &APIGatewayEvent) -> Option<&str> {
+    let authorization = event.header("Authorization")?.trim();
+
+    match authorization.find(' ') {
+        Some(index) if authorization[..index].eq_ignore_ascii_case("bearer") => {
+            Some(authorization[index + 1..].trim())
+        }
+        _ => None,
+    }
+}
+
+thread_local! {
+    static SHARED_AUTH_CONFIG: RefCell<Option<Option<Rc<AuthConfig>>>> = const { RefCell::new(None) };
+}
+
+/// Reads `AuthConfig::from_env` once per container, so the JWKS file is not read on every invocation.
+pub fn shared_auth_config() -> Result<Option<Rc<AuthConfig>>, AuthError> {
+    SHARED_AUTH_CONFIG.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        if let Some(ref config) = *shared {
+            return Ok(config.clone());
+        }
+        let config = AuthConfig::from_env()?.map(Rc::new);
+        *shared = Some(config.clone());
+        Ok(config)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+    use ring::rand::SystemRandom;
+    use serde_json::{json, to_vec};
+
+    const NOW: i64 = 1_550_000_000;
+
+    fn encode(value: &serde_json::Value) -> String {
+        URL_SAFE_NO_PAD.encode(to_vec(value).unwrap())
+    }
+
+    fn hs256(secret: &[u8], claims: serde_json::Value) -> String {
+        let signed = format!(
+            "{}.{}",
+            encode(&json!({"alg": "HS256", "typ": "JWT"})),
+            encode(&claims)
+        );
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+        let signature = hmac::sign(&key, signed.as_bytes());
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+    }
+
+    fn rsa_key_pair() -> signature::RsaKeyPair {
+        let pem = include_str!("../fixtures/jwt_test_key.pem");
+        let der: String = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        signature::RsaKeyPair::from_pkcs8(&STANDARD.decode(der).unwrap()).unwrap()
+    }
+
+    fn rs256(key_pair: &signature::RsaKeyPair, kid: &str, claims: serde_json::Value) -> String {
+        let signed = format!(
+            "{}.{}",
+            encode(&json!({"alg": "RS256", "typ": "JWT", "kid": kid})),
+            encode(&claims)
+        );
+        let mut signature = vec![0; key_pair.public().modulus_len()];
+        key_pair
+            .sign(
+                &signature::RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                signed.as_bytes(),
+                &mut signature,
+            )
+            .unwrap();
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(&signature))
+    }
+
+    fn claims(roles: &[&str]) -> serde_json::Value {
+        json!({
+            "sub": "editor@example.com",
+            "iss": "https://auth.example.com/",
+            "aud": ["quizzical", "other"],
+            "exp": NOW + 300,
+            "roles": roles,
+        })
+    }
+
+    fn hs256_config() -> AuthConfig {
+        AuthConfig::new(
+            "https://auth.example.com/",
+            "quizzical",
+            vec![Key {
+                kid: None,
+                material: KeyMaterial::Hmac(b"secret".to_vec()),
+            }],
+        )
+    }
+
+    fn event(authorization: Option<String>) -> APIGatewayEvent {
+        APIGatewayEvent {
+            headers: authorization.map(|authorization| {
+                let mut headers = HashMap::new();
+                headers.insert("authorization".to_string(), authorization);
+                headers
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_valid_hs256_token() {
+        let token = hs256(b"secret", claims(&["editor"]));
+
+        let claims = hs256_config().validate(&token, NOW).unwrap();
+        assert_eq!(claims.sub, Some("editor@example.com".to_string()));
+        assert_eq!(claims.roles, vec!["editor".to_string()]);
+    }
+
+    #[test]
+    fn test_rejects_wrong_signature_issuer_and_audience() {
+        let config = hs256_config();
+
+        let forged = hs256(b"guess", claims(&["admin"]));
+        assert!(config.validate(&forged, NOW).is_err());
+
+        let mut other_issuer = claims(&["admin"]);
+        other_issuer["iss"] = json!("https://evil.example.com/");
+        assert_eq!(
+            config.validate(&hs256(b"secret", other_issuer), NOW),
+            Err(invalid("unexpected issuer"))
+        );
+
+        let mut other_audience = claims(&["admin"]);
+        other_audience["aud"] = json!("other");
+        assert_eq!(
+            config.validate(&hs256(b"secret", other_audience), NOW),
+            Err(invalid("unexpected audience"))
+        );
+
+        let unsigned = format!(
+            "{}.{}.",
+            encode(&json!({"alg": "none"})),
+            encode(&claims(&["admin"]))
+        );
+        assert!(config.validate(&unsigned, NOW).is_err());
+    }
+
+    #[test]
+    fn test_expiry_allows_clock_skew() {
+        let config = hs256_config();
+        let token = hs256(b"secret", claims(&["editor"]));
+
+        assert!(config
+            .validate(&token, NOW + 300 + DEFAULT_LEEWAY_SECONDS)
+            .is_ok());
+        assert_eq!(
+            config.validate(&token, NOW + 301 + DEFAULT_LEEWAY_SECONDS),
+            Err(invalid("token expired"))
+        );
+
+        let mut not_before = claims(&["editor"]);
+        not_before["nbf"] = json!(NOW + 120);
+        assert_eq!(
+            config.validate(&hs256(b"secret", not_before), NOW),
+            Err(invalid("token not yet valid"))
+        );
+    }
+
+    #[test]
+    fn test_rs256_token_with_jwks() {
+        let key_pair = rsa_key_pair();
+        let public: signature::RsaPublicKeyComponents<Vec<u8>> = key_pair.public().into();
+        let jwks = to_vec(&json!({
+            "keys": [{
+                "kty": "RSA",
+                "kid": "2019-02",
+                "use": "sig",
+                "n": URL_SAFE_NO_PAD.encode(&public.n),
+                "e": URL_SAFE_NO_PAD.encode(&public.e),
+            }]
+        }))
+        .unwrap();
+        let config = AuthConfig::new(
+            "https://auth.example.com/",
+            "quizzical",
+            parse_jwks(&jwks).unwrap(),
+        );
+
+        let token = rs256(&key_pair, "2019-02", claims(&["admin"]));
+        assert!(config.validate(&token, NOW).is_ok());
+
+        let unknown_kid = rs256(&key_pair, "2018-01", claims(&["admin"]));
+        assert!(config.validate(&unknown_kid, NOW).is_err());
+
+        // The public key must not be accepted as an HS256 secret.
+        let confused = hs256(&public.n, claims(&["admin"]));
+        assert!(config.validate(&confused, NOW).is_err());
+    }
+
+    #[test]
+    fn test_roles_map_to_permissions() {
+        let mut config = hs256_config();
+        let editor: Claims = serde_json::from_value(claims(&["editor", "unknown"])).unwrap();
+        let admin: Claims = serde_json::from_value(claims(&["admin"])).unwrap();
+
+        assert_eq!(
+            config.permissions(&editor),
+            [Permission::QuestionsWrite].iter().cloned().collect()
+        );
+        assert!(config
+            .permissions(&admin)
+            .contains(&Permission::CategoriesAdmin));
+
+        config.roles = parse_roles("editor=questions:write,categories:admin").unwrap();
+        assert!(config
+            .permissions(&editor)
+            .contains(&Permission::CategoriesAdmin));
+        assert!(parse_roles("editor=questions:delete").is_err());
+    }
+
+    #[test]
+    fn test_authorize_distinguishes_401_and_403() {
+        let config = hs256_config();
+        let editor = |claims: serde_json::Value| {
+            let mut claims = claims;
+            claims["exp"] = json!(Utc::now().timestamp() + 300);
+            Some(format!("Bearer {}", hs256(b"secret", claims)))
+        };
+
+        assert_eq!(
+            config.authorize(&event(None), Permission::QuestionsWrite),
+            Err(AuthError::MissingToken)
+        );
+        assert_eq!(
+            config.authorize(
+                &event(Some("Basic YWxpY2U6c2VjcmV0".into())),
+                Permission::QuestionsWrite
+            ),
+            Err(AuthError::MissingToken)
+        );
+        assert!(config
+            .authorize(
+                &event(editor(claims(&["editor"]))),
+                Permission::QuestionsWrite
+            )
+            .is_ok());
+
+        let forbidden = config.authorize(
+            &event(editor(claims(&["editor"]))),
+            Permission::CategoriesAdmin,
+        );
+        assert_eq!(
+            forbidden,
+            Err(AuthError::Forbidden(Permission::CategoriesAdmin))
+        );
+        assert_eq!(
+            APIErrorResponse::from(forbidden.unwrap_err()).status_code(),
+            403
+        );
+        assert_eq!(
+            APIErrorResponse::from(AuthError::MissingToken).status_code(),
+            401
+        );
+    }
+
+    #[test]
+    fn test_authenticate_returns_the_subject() {
+        let config = hs256_config();
+        let mut valid = claims(&[]);
+        valid["exp"] = json!(Utc::now().timestamp() + 300);
+
+        assert_eq!(config.authenticate(&event(None)), Ok(None));
+        assert_eq!(
+            config.authenticate(&event(Some(format!("Bearer {}", hs256(b"secret", valid))))),
+            Ok(Some("editor@example.com".to_string()))
+        );
+        assert!(config
+            .authenticate(&event(Some(format!(
+                "Bearer {}",
+                hs256(b"other", claims(&[]))
+            ))))
+            .is_err());
+    }
+}
@@ -0,0 +1,792 @@
+use apigateway::APIGatewayEvent;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use router::Router;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroU32;
+
+/// The only message for which API Gateway responds with `401 Unauthorized`.
+pub const UNAUTHORIZED: &str = "Unauthorized";
+
+/// The iterations used by `PasswordHash::new`.
+pub const DEFAULT_ITERATIONS: u32 = 100_000;
+
+const HASH_SCHEME: &str = "pbkdf2-sha256";
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+
+/* #region AuthorizerEvent */
+
+/**
+ * The event sent to a custom authorizer.
+ *
+ * `TOKEN` authorizers receive the `Authorization` header as `authorizationToken`.
+ * `REQUEST` authorizers receive all the headers of the request instead.
+ */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuthorizerEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(rename = "methodArn")]
+    pub method_arn: String,
+    #[serde(rename = "authorizationToken")]
+    pub authorization_token: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+}
+
+impl AuthorizerEvent {
+    /// The `Authorization` header, wherever the event type puts it.
+    pub fn authorization(&self) -> Option<&str> {
+        match self.event_type.as_str() {
+            "REQUEST" => self.headers.as_ref().and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+                    .map(|(_, value)| value.as_str())
+            }),
+            _ => self.authorization_token.as_deref(),
+        }
+    }
+}
+
+/* #region AuthorizerError */
+
+#[derive(Debug, PartialEq)]
+pub enum AuthorizerError {
+    /// Missing or wrong credentials. API Gateway responds with `401`.
+    Unauthorized,
+    InvalidMethodArn(String),
+    InvalidResource(String),
+    InvalidPasswordHash(String),
+    EmptyPolicy,
+}
+
+impl fmt::Display for AuthorizerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthorizerError::Unauthorized => write!(f, "{}", UNAUTHORIZED),
+            AuthorizerError::InvalidMethodArn(arn) => write!(f, "Invalid method ARN: {}", arn),
+            AuthorizerError::InvalidResource(resource) => write!(
+                f,
+                "Invalid resource path: {}. Path should match ^[/.a-zA-Z0-9-\\*]+$",
+                resource
+            ),
+            AuthorizerError::InvalidPasswordHash(user) => {
+                write!(f, "Invalid password hash for user '{}'", user)
+            }
+            AuthorizerError::EmptyPolicy => write!(f, "No statements defined for the policy"),
+        }
+    }
+}
+
+impl std::error::Error for AuthorizerError {}
+
+/* #region Users */
+
+/**
+ * A salted PBKDF2-HMAC-SHA256 hash, written as `pbkdf2-sha256$<iterations>$<salt>$<hash>`
+ * with the salt and hash in standard base64.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordHash {
+    iterations: NonZeroU32,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl PasswordHash {
+    /// Hashes `password` with a random salt.
+    pub fn new(password: &str) -> PasswordHash {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .expect("Failed to generate salt");
+        PasswordHash::with_salt(password, salt, DEFAULT_ITERATIONS)
+    }
+
+    pub fn with_salt(password: &str, salt: Vec<u8>, iterations: u32) -> PasswordHash {
+        let iterations = NonZeroU32::new(iterations).expect("iterations must not be 0");
+        let mut hash = vec![0u8; HASH_LENGTH];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            password.as_bytes(),
+            &mut hash,
+        );
+        PasswordHash {
+            iterations,
+            salt,
+            hash,
+        }
+    }
+
+    pub fn parse(encoded: &str) -> Option<PasswordHash> {
+        let parts: Vec<&str> = encoded.split('$').collect();
+        match parts.as_slice() {
+            [scheme, iterations, salt, hash] if *scheme == HASH_SCHEME => Some(PasswordHash {
+                iterations: iterations.parse().ok()?,
+                salt: STANDARD.decode(salt).ok()?,
+                hash: STANDARD.decode(hash).ok().filter(|hash| !hash.is_empty())?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Compares in constant time, so the response time does not reveal how close a guess was.
+    pub fn verify(&self, password: &str) -> bool {
+        pbkdf2::verify(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            self.iterations,
+            &self.salt,
+            password.as_bytes(),
+            &self.hash,
+        )
+        .is_ok()
+    }
+}
+
+impl fmt::Display for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}${}${}${}",
+            HASH_SCHEME,
+            self.iterations,
+            STANDARD.encode(&self.salt),
+            STANDARD.encode(&self.hash)
+        )
+    }
+}
+
+/**
+ * The users allowed to call the protected endpoints.
+ *
+ * Users are configured as a comma separated list of `<username>:<password hash>` pairs
+ * (see `PasswordHash`), e.g. in the `AUTHORIZER_USERS` environment variable.
+ */
+#[derive(Debug, Default)]
+pub struct Users {
+    users: HashMap<String, PasswordHash>,
+    /// Checked when the user does not exist, so unknown users take as long as wrong passwords.
+    decoy: Option<PasswordHash>,
+}
+
+impl Users {
+    pub fn parse(config: &str) -> Result<Users, AuthorizerError> {
+        let mut users = HashMap::new();
+        for entry in config.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (username, hash) = match entry.find(':') {
+                Some(index) => (&entry[..index], &entry[index + 1..]),
+                None => return Err(AuthorizerError::InvalidPasswordHash(entry.into())),
+            };
+            let hash = PasswordHash::parse(hash)
+                .ok_or_else(|| AuthorizerError::InvalidPasswordHash(username.into()))?;
+            users.insert(username.to_string(), hash);
+        }
+
+        let decoy = users.values().next().map(|hash| PasswordHash {
+            hash: vec![0u8; hash.hash.len()],
+            ..hash.clone()
+        });
+        Ok(Users { users, decoy })
+    }
+
+    /// Returns the username if the `Authorization` header holds valid Basic credentials.
+    pub fn authenticate(&self, authorization: &str) -> Result<String, AuthorizerError> {
+        let (username, password) =
+            basic_credentials(authorization).ok_or(AuthorizerError::Unauthorized)?;
+
+        let verified = match (self.users.get(&username), &self.decoy) {
+            (Some(hash), _) => hash.verify(&password),
+            (None, Some(decoy)) => {
+                let _ = decoy.verify(&password);
+                false
+            }
+            (None, None) => false,
+        };
+
+        match verified {
+            true => Ok(username),
+            false => Err(AuthorizerError::Unauthorized),
+        }
+    }
+}
+
+/// Decodes `Basic <base64(username:password)>`.
+fn basic_credentials(authorization: &str) -> Option<(String, String)> {
+    let mut parts = authorization.trim().splitn(2, ' ');
+    let scheme = parts.next()?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = STANDARD.decode(parts.next()?.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let index = decoded.find(':')?;
+    Some((
+        decoded[..index].to_string(),
+        decoded[index + 1..].to_string(),
+    ))
+}
+
+/* #region AuthPolicy */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpVerb {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Head,
+    Delete,
+    Options,
+    All,
+}
+
+impl HttpVerb {
+    /// The verb of an HTTP method such as `GET`, ignoring case.
+    pub fn parse(method: &str) -> Option<HttpVerb> {
+        match method.to_uppercase().as_str() {
+            "GET" => Some(HttpVerb::Get),
+            "POST" => Some(HttpVerb::Post),
+            "PUT" => Some(HttpVerb::Put),
+            "PATCH" => Some(HttpVerb::Patch),
+            "HEAD" => Some(HttpVerb::Head),
+            "DELETE" => Some(HttpVerb::Delete),
+            "OPTIONS" => Some(HttpVerb::Options),
+            "*" => Some(HttpVerb::All),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HttpVerb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verb = match self {
+            HttpVerb::Get => "GET",
+            HttpVerb::Post => "POST",
+            HttpVerb::Put => "PUT",
+            HttpVerb::Patch => "PATCH",
+            HttpVerb::Head => "HEAD",
+            HttpVerb::Delete => "DELETE",
+            HttpVerb::Options => "OPTIONS",
+            HttpVerb::All => "*",
+        };
+        write!(f, "{}", verb)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Statement {
+    #[serde(rename = "Action")]
+    pub action: String,
+    #[serde(rename = "Effect")]
+    pub effect: String,
+    #[serde(rename = "Resource")]
+    pub resource: Vec<String>,
+    #[serde(rename = "Condition", skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Map<String, Value>>,
+}
+
+impl Statement {
+    fn empty(effect: Effect) -> Statement {
+        Statement {
+            action: "execute-api:Invoke".into(),
+            effect: format!("{:?}", effect),
+            resource: vec![],
+            condition: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PolicyDocument {
+    #[serde(rename = "Version")]
+    pub version: String,
+    #[serde(rename = "Statement")]
+    pub statement: Vec<Statement>,
+}
+
+/// The response of the authorizer, in the format produced by `AuthPolicy.build` in the AWS blueprint.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuthResponse {
+    #[serde(rename = "principalId")]
+    pub principal_id: String,
+    #[serde(rename = "policyDocument")]
+    pub policy_document: PolicyDocument,
+}
+
+struct Method {
+    resource_arn: String,
+    conditions: Option<Map<String, Value>>,
+}
+
+/**
+ * Builds the IAM policy returned by the authorizer. This is a port of the `AuthPolicy` class of
+ * the API Gateway authorizer blueprint.
+ *
+ * The policy is cached by API Gateway (5 minutes by default) and applies to every method of the
+ * API called with the same token, so it lists everything the principal may call, not only the
+ * method that triggered the authorizer.
+ */
+pub struct AuthPolicy {
+    pub principal_id: String,
+    pub aws_account_id: String,
+    pub rest_api_id: String,
+    pub region: String,
+    pub stage: String,
+    allow_methods: Vec<Method>,
+    deny_methods: Vec<Method>,
+}
+
+impl AuthPolicy {
+    pub const VERSION: &'static str = "2012-10-17";
+
+    pub fn new(principal_id: &str, aws_account_id: &str) -> AuthPolicy {
+        AuthPolicy {
+            principal_id: principal_id.into(),
+            aws_account_id: aws_account_id.into(),
+            rest_api_id: "*".into(),
+            region: "*".into(),
+            stage: "*".into(),
+            allow_methods: vec![],
+            deny_methods: vec![],
+        }
+    }
+
+    /// Scopes the policy to the API, region and stage of a method ARN
+    /// e.g. `arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/GET/categories`.
+    pub fn for_method_arn(
+        principal_id: &str,
+        method_arn: &str,
+    ) -> Result<AuthPolicy, AuthorizerError> {
+        let invalid = || AuthorizerError::InvalidMethodArn(method_arn.into());
+
+        let parts: Vec<&str> = method_arn.split(':').collect();
+        if parts.len() < 6 {
+            return Err(invalid());
+        }
+        let api_gateway_arn: Vec<&str> = parts[5].split('/').collect();
+        if api_gateway_arn.len() < 2 {
+            return Err(invalid());
+        }
+
+        let mut policy = AuthPolicy::new(principal_id, parts[4]);
+        policy.region = parts[3].into();
+        policy.rest_api_id = api_gateway_arn[0].into();
+        policy.stage = api_gateway_arn[1].into();
+        Ok(policy)
+    }
+
+    fn add_method(
+        &mut self,
+        effect: Effect,
+        verb: HttpVerb,
+        resource: &str,
+        conditions: Option<Map<String, Value>>,
+    ) -> Result<(), AuthorizerError> {
+        let valid = !resource.is_empty()
+            && resource
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/.-*".contains(c));
+        if !valid {
+            return Err(AuthorizerError::InvalidResource(resource.into()));
+        }
+
+        let resource_arn = format!(
+            "arn:aws:execute-api:{}:{}:{}/{}/{}/{}",
+            self.region,
+            self.aws_account_id,
+            self.rest_api_id,
+            self.stage,
+            verb,
+            resource.trim_start_matches('/')
+        );
+        let method = Method {
+            resource_arn,
+            conditions: conditions.filter(|conditions| !conditions.is_empty()),
+        };
+
+        match effect {
+            Effect::Allow => self.allow_methods.push(method),
+            Effect::Deny => self.deny_methods.push(method),
+        }
+        Ok(())
+    }
+
+    pub fn allow_all_methods(&mut self) -> Result<(), AuthorizerError> {
+        self.add_method(Effect::Allow, HttpVerb::All, "*", None)
+    }
+
+    pub fn deny_all_methods(&mut self) -> Result<(), AuthorizerError> {
+        self.add_method(Effect::Deny, HttpVerb::All, "*", None)
+    }
+
+    pub fn allow_method(&mut self, verb: HttpVerb, resource: &str) -> Result<(), AuthorizerError> {
+        self.add_method(Effect::Allow, verb, resource, None)
+    }
+
+    pub fn deny_method(&mut self, verb: HttpVerb, resource: &str) -> Result<(), AuthorizerError> {
+        self.add_method(Effect::Deny, verb, resource, None)
+    }
+
+    /// See the IAM reference for the `Condition` element of policies.
+    pub fn allow_method_with_conditions(
+        &mut self,
+        verb: HttpVerb,
+        resource: &str,
+        conditions: Map<String, Value>,
+    ) -> Result<(), AuthorizerError> {
+        self.add_method(Effect::Allow, verb, resource, Some(conditions))
+    }
+
+    pub fn deny_method_with_conditions(
+        &mut self,
+        verb: HttpVerb,
+        resource: &str,
+        conditions: Map<String, Value>,
+    ) -> Result<(), AuthorizerError> {
+        self.add_method(Effect::Deny, verb, resource, Some(conditions))
+    }
+
+    /// Methods without conditions share one statement per effect; each conditional method gets its own.
+    fn statements(effect: Effect, methods: Vec<Method>) -> Vec<Statement> {
+        let mut statements = vec![];
+        let mut statement = Statement::empty(effect);
+
+        for method in methods {
+            match method.conditions {
+                None => statement.resource.push(method.resource_arn),
+                Some(conditions) => statements.push(Statement {
+                    resource: vec![method.resource_arn],
+                    condition: Some(conditions),
+                    ..Statement::empty(effect)
+                }),
+            }
+        }
+
+        if !statement.resource.is_empty() {
+            statements.push(statement);
+        }
+        statements
+    }
+
+    pub fn build(self) -> Result<AuthResponse, AuthorizerError> {
+        if self.allow_methods.is_empty() && self.deny_methods.is_empty() {
+            return Err(AuthorizerError::EmptyPolicy);
+        }
+
+        let mut statement = AuthPolicy::statements(Effect::Allow, self.allow_methods);
+        statement.extend(AuthPolicy::statements(Effect::Deny, self.deny_methods));
+
+        Ok(AuthResponse {
+            principal_id: self.principal_id,
+            policy_document: PolicyDocument {
+                version: AuthPolicy::VERSION.into(),
+                statement,
+            },
+        })
+    }
+}
+
+/* #region Authorizer */
+
+/// The policy resource of a route template, e.g. `/questions/*` for `/questions/{id}`.
+fn template_resource(template: &str) -> String {
+    template
+        .split('/')
+        .map(
+            |segment| match segment.starts_with('{') && segment.ends_with('}') {
+                true => "*",
+                false => segment,
+            },
+        )
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// The method and path called, from the end of a method ARN, e.g. `.../prod/GET/questions/12`.
+fn method_arn_request(method_arn: &str) -> Result<(HttpVerb, String), AuthorizerError> {
+    let invalid = || AuthorizerError::InvalidMethodArn(method_arn.into());
+
+    let mut parts = method_arn
+        .splitn(6, ':')
+        .nth(5)
+        .ok_or_else(invalid)?
+        .splitn(4, '/')
+        .skip(2);
+    let verb = parts.next().and_then(HttpVerb::parse).ok_or_else(invalid)?;
+    Ok((verb, format!("/{}", parts.next().unwrap_or_default())))
+}
+
+/**
+ * Authenticates the caller and grants access to the routes of `router` that require a permission.
+ *
+ * The policy lists every protected route, as it is cached for the token. Routes that do not
+ * require a permission are not meant to be behind the authorizer, so the called method is denied
+ * if `Router::permission` does not require one for it.
+ */
+pub fn authorize(
+    event: &AuthorizerEvent,
+    users: &Users,
+    router: &Router,
+) -> Result<AuthResponse, AuthorizerError> {
+    let authorization = event.authorization().ok_or(AuthorizerError::Unauthorized)?;
+    let principal_id = users.authenticate(authorization)?;
+
+    let mut policy = AuthPolicy::for_method_arn(&principal_id, &event.method_arn)?;
+    for (method, template) in router.protected_routes() {
+        let verb = HttpVerb::parse(method)
+            .ok_or_else(|| AuthorizerError::InvalidResource(method.into()))?;
+        policy.allow_method(verb, &template_resource(template))?;
+    }
+
+    let (verb, path) = method_arn_request(&event.method_arn)?;
+    let requested = APIGatewayEvent {
+        http_method: verb.to_string(),
+        path: path.clone(),
+        ..Default::default()
+    };
+    if router.permission(&requested).is_none() {
+        policy.deny_method(verb, &path)?;
+    }
+    policy.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::api_router;
+    use serde_json::{json, to_value};
+
+    const METHOD_ARN: &str =
+        "arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/POST/questions";
+
+    fn users() -> Users {
+        let alice = PasswordHash::with_salt("wonderland", b"salt".to_vec(), 1000);
+        let bob = PasswordHash::with_salt("builder", b"pepper".to_vec(), 1000);
+        Users::parse(&format!("alice:{}, bob:{}", alice, bob)).unwrap()
+    }
+
+    fn basic(credentials: &str) -> String {
+        format!("Basic {}", STANDARD.encode(credentials))
+    }
+
+    fn token_event(authorization: &str) -> AuthorizerEvent {
+        AuthorizerEvent {
+            event_type: "TOKEN".into(),
+            method_arn: METHOD_ARN.into(),
+            authorization_token: Some(authorization.into()),
+            headers: None,
+        }
+    }
+
+    #[test]
+    fn test_password_hash_round_trip() {
+        let hash = PasswordHash::new("secret");
+        let parsed = PasswordHash::parse(&hash.to_string()).unwrap();
+
+        assert_eq!(parsed, hash);
+        assert!(parsed.verify("secret"));
+        assert!(!parsed.verify("Secret"));
+    }
+
+    #[test]
+    fn test_invalid_users_config() {
+        assert!(Users::parse("alice").is_err());
+        assert!(Users::parse("alice:plaintext").is_err());
+        assert!(Users::parse("alice:pbkdf2-sha256$0$c2FsdA==$aGFzaA==").is_err());
+    }
+
+    #[test]
+    fn test_multiple_users_can_authenticate() {
+        let users = users();
+
+        assert_eq!(
+            users.authenticate(&basic("alice:wonderland")),
+            Ok("alice".into())
+        );
+        assert_eq!(users.authenticate(&basic("bob:builder")), Ok("bob".into()));
+        assert_eq!(
+            users.authenticate(&basic("bob:wonderland")),
+            Err(AuthorizerError::Unauthorized)
+        );
+        assert_eq!(
+            users.authenticate(&basic("eve:wonderland")),
+            Err(AuthorizerError::Unauthorized)
+        );
+        assert_eq!(
+            users.authenticate("Bearer abc"),
+            Err(AuthorizerError::Unauthorized)
+        );
+        assert_eq!(
+            users.authenticate("Basic not-base64"),
+            Err(AuthorizerError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn test_request_event_reads_authorization_header() {
+        let mut headers = HashMap::new();
+        headers.insert("authorization".to_string(), basic("alice:wonderland"));
+        let event = AuthorizerEvent {
+            event_type: "REQUEST".into(),
+            method_arn: METHOD_ARN.into(),
+            authorization_token: None,
+            headers: Some(headers),
+        };
+
+        assert_eq!(
+            authorize(&event, &users(), &api_router())
+                .unwrap()
+                .principal_id,
+            "alice"
+        );
+    }
+
+    #[test]
+    fn test_missing_or_invalid_credentials_are_unauthorized() {
+        let missing = AuthorizerEvent {
+            event_type: "TOKEN".into(),
+            method_arn: METHOD_ARN.into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            authorize(&missing, &users(), &api_router()),
+            Err(AuthorizerError::Unauthorized)
+        );
+        assert_eq!(
+            authorize(&token_event(&basic("alice:queen")), &users(), &api_router()),
+            Err(AuthorizerError::Unauthorized)
+        );
+        assert_eq!(format!("{}", AuthorizerError::Unauthorized), "Unauthorized");
+    }
+
+    #[test]
+    fn test_policy_matches_blueprint_format() {
+        let response = authorize(
+            &token_event(&basic("alice:wonderland")),
+            &users(),
+            &api_router(),
+        )
+        .unwrap();
+        let arn = |verb: &str, resource: &str| {
+            format!(
+                "arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/{}/{}",
+                verb, resource
+            )
+        };
+
+        assert_eq!(
+            to_value(&response).unwrap(),
+            json!({
+                "principalId": "alice",
+                "policyDocument": {
+                    "Version": "2012-10-17",
+                    "Statement": [{
+                        "Action": "execute-api:Invoke",
+                        "Effect": "Allow",
+                        "Resource": [
+                            arn("POST", "categories"),
+                            arn("PATCH", "categories/*"),
+                            arn("DELETE", "categories/*"),
+                            arn("GET", "admin/categories"),
+                            arn("POST", "categories/*/active"),
+                            arn("GET", "admin/questions"),
+                            arn("GET", "admin/questions/*"),
+                            arn("POST", "questions"),
+                            arn("PUT", "questions/*"),
+                            arn("DELETE", "questions/*"),
+                        ]
+                    }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_public_methods_are_denied() {
+        let mut event = token_event(&basic("alice:wonderland"));
+        let allowed = |event: &AuthorizerEvent| {
+            let response = authorize(event, &users(), &api_router()).unwrap();
+            let statements = to_value(&response.policy_document.statement).unwrap();
+            statements
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|statement| statement["Effect"] == "Allow")
+        };
+
+        event.method_arn =
+            "arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/DELETE/questions/12".into();
+        assert!(allowed(&event));
+
+        event.method_arn =
+            "arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/GET/questions/12".into();
+        assert!(!allowed(&event));
+
+        event.method_arn = "arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod".into();
+        assert_eq!(
+            authorize(&event, &users(), &api_router()),
+            Err(AuthorizerError::InvalidMethodArn(event.method_arn.clone()))
+        );
+    }
+
+    #[test]
+    fn test_conditional_and_deny_statements() {
+        let mut policy = AuthPolicy::for_method_arn("alice", METHOD_ARN).unwrap();
+        let mut conditions = Map::new();
+        conditions.insert("IpAddress".into(), json!({"aws:SourceIp": "10.0.0.0/8"}));
+
+        policy.allow_method(HttpVerb::Get, "/categories").unwrap();
+        policy
+            .allow_method_with_conditions(HttpVerb::Post, "/questions", conditions)
+            .unwrap();
+        policy.deny_all_methods().unwrap();
+
+        let statements = to_value(&policy.build().unwrap().policy_document.statement).unwrap();
+        assert_eq!(
+            statements,
+            json!([{
+                "Action": "execute-api:Invoke",
+                "Effect": "Allow",
+                "Resource": ["arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/POST/questions"],
+                "Condition": {"IpAddress": {"aws:SourceIp": "10.0.0.0/8"}}
+            }, {
+                "Action": "execute-api:Invoke",
+                "Effect": "Allow",
+                "Resource": ["arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/GET/categories"]
+            }, {
+                "Action": "execute-api:Invoke",
+                "Effect": "Deny",
+                "Resource": ["arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/*/*"]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_invalid_policies() {
+        assert_eq!(
+            AuthPolicy::new("alice", "123456789012").build().err(),
+            Some(AuthorizerError::EmptyPolicy)
+        );
+        assert!(AuthPolicy::for_method_arn("alice", "not-an-arn").is_err());
+
+        let mut policy = AuthPolicy::new("alice", "123456789012");
+        assert!(policy
+            .allow_method(HttpVerb::Get, "/questions?page=1")
+            .is_err());
+    }
+}
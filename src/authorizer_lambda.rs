@@ -0,0 +1,65 @@
+extern crate lambda_runtime as lambda;
+extern crate log;
+extern crate quizzical;
+extern crate simple_logger;
+
+use lambda::{start, Context};
+use log::{info, warn};
+use quizzical::authorizer::*;
+use quizzical::handlers::api_router;
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead};
+
+fn users() -> Result<Users, AuthorizerError> {
+    Users::parse(&env::var("AUTHORIZER_USERS").unwrap_or_default())
+}
+
+const USAGE: &str = "usage: authorizer [hash]";
+
+/**
+ * API Gateway custom authorizer for the write and `/admin` endpoints, i.e. the routes of
+ * `api_router` that require a permission.
+ *
+ * The users are read from `AUTHORIZER_USERS` (see `Users`). Credentials are never logged.
+ *
+ * `authorizer hash` reads a password from stdin and prints its hash for `AUTHORIZER_USERS`.
+ */
+fn main() -> Result<(), Box<dyn Error>> {
+    match env::args().nth(1).as_deref() {
+        None => {}
+        Some("hash") => {
+            let mut password = String::new();
+            io::stdin().lock().read_line(&mut password)?;
+            println!(
+                "{}",
+                PasswordHash::new(password.trim_end_matches(&['\r', '\n'][..]))
+            );
+            return Ok(());
+        }
+        Some(_) => return Err(USAGE.into()),
+    }
+
+    simple_logger::init_with_level(log::Level::Info).unwrap();
+
+    // Fail on startup rather than on every invocation if the users are misconfigured.
+    users()?;
+
+    start(
+        |event: AuthorizerEvent, c: Context| {
+            info!(
+                "{} authorizer event for {}",
+                event.event_type, event.method_arn
+            );
+            users()
+                .and_then(|users| authorize(&event, &users, &api_router()))
+                .inspect(|response| info!("Authorized '{}'", response.principal_id))
+                .map_err(|e| {
+                    warn!("{}", e);
+                    c.new_error(&format!("{}", e))
+                })
+        },
+        None,
+    );
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use apigateway::APIGatewayResponse;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use brotli::CompressorWriter;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::env;
+use std::io::Write;
+
+/// Brotli quality 5 compresses about as well as gzip's default level, but faster.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// Supported encodings, in order of preference when a client accepts several equally.
+    pub const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// The `Content-Encoding` token.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /**
+     * The entity tag of the representation in this encoding.
+     *
+     * A strong tag must differ between encodings of the same body, so the encoding is appended
+     * to the tag of the uncompressed body: `"abc"` becomes `"abc-gzip"`.
+     */
+    pub fn etag(self, etag: &str) -> String {
+        match etag.strip_suffix('"') {
+            Some(opaque) => format!("{}-{}\"", opaque, self.name()),
+            None => etag.to_string(),
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Brotli => {
+                let mut writer = CompressorWriter::new(
+                    Vec::new(),
+                    BROTLI_BUFFER_SIZE,
+                    BROTLI_QUALITY,
+                    BROTLI_WINDOW,
+                );
+                writer
+                    .write_all(data)
+                    .expect("writing to a Vec can not fail");
+                writer.into_inner()
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder
+                    .write_all(data)
+                    .expect("writing to a Vec can not fail");
+                encoder.finish().expect("writing to a Vec can not fail")
+            }
+        }
+    }
+
+    /**
+     * Picks the encoding for an `Accept-Encoding` header.
+     *
+     * The encoding with the highest quality value wins, ties going to brotli. `*` applies to
+     * the encodings that are not listed, and `q=0` refuses an encoding.
+     */
+    pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+        let mut wildcard = None;
+        let mut listed: Vec<(&str, f32)> = vec![];
+        for item in accept_encoding.split(',') {
+            let mut parts = item.split(';').map(str::trim);
+            let coding = parts.next().unwrap_or("");
+            let quality = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .filter_map(|q| q.parse::<f32>().ok())
+                .next()
+                .unwrap_or(1.0);
+            match coding {
+                "" => continue,
+                "*" => wildcard = Some(quality),
+                coding => listed.push((coding, quality)),
+            }
+        }
+
+        let mut best: Option<(Encoding, f32)> = None;
+        for encoding in Encoding::ALL.iter() {
+            let quality = listed
+                .iter()
+                .find(|(coding, _)| coding.eq_ignore_ascii_case(encoding.name()))
+                .map(|(_, quality)| *quality)
+                .or(wildcard)
+                .unwrap_or(0.0);
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((*encoding, quality));
+            }
+        }
+        best.map(|(encoding, _)| encoding)
+    }
+}
+
+/// When response bodies are compressed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionPolicy {
+    /// Bodies shorter than this many bytes are sent as they are. `None` disables compression.
+    pub min_size: Option<usize>,
+}
+
+/// Compresses bodies of 1 KiB or more; smaller bodies rarely fit in fewer packets once compressed.
+impl Default for CompressionPolicy {
+    fn default() -> CompressionPolicy {
+        CompressionPolicy {
+            min_size: Some(1024),
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Reads `COMPRESSION_MIN_SIZE`, in bytes, or `off` to disable compression.
+    pub fn from_env() -> Result<CompressionPolicy, String> {
+        match env::var("COMPRESSION_MIN_SIZE") {
+            Ok(ref value) if value.eq_ignore_ascii_case("off") => {
+                Ok(CompressionPolicy { min_size: None })
+            }
+            Ok(value) => Ok(CompressionPolicy {
+                min_size: Some(value.parse().map_err(|_| {
+                    "COMPRESSION_MIN_SIZE must be a number of bytes or 'off'".to_string()
+                })?),
+            }),
+            Err(_) => Ok(CompressionPolicy::default()),
+        }
+    }
+
+    /**
+     * Compresses the body in the best encoding accepted by the client.
+     *
+     * The compressed body is base64 encoded, as API Gateway only carries binary bodies that way,
+     * and the `ETag` is suffixed with the encoding. `Vary: Accept-Encoding` is added to every
+     * response that is large enough to be compressed, whether or not it was.
+     */
+    pub fn apply(&self, accept_encoding: Option<&str>, response: &mut APIGatewayResponse) {
+        let min_size = match self.min_size {
+            Some(min_size) => min_size,
+            None => return,
+        };
+        if response.body.is_empty()
+            || response.body.len() < min_size
+            || response.is_base64_encoded
+            || response
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("Content-Encoding"))
+        {
+            return;
+        }
+
+        response.add_vary("Accept-Encoding");
+        let encoding = match accept_encoding.and_then(Encoding::negotiate) {
+            Some(encoding) => encoding,
+            None => return,
+        };
+
+        let compressed = encoding.compress(response.body.as_bytes());
+        if compressed.len() >= response.body.len() {
+            return;
+        }
+
+        response.body = STANDARD.encode(compressed);
+        response.is_base64_encoded = true;
+        response
+            .headers
+            .insert("Content-Encoding".into(), encoding.name().into());
+        if let Some(etag) = response.headers.get_mut("ETag") {
+            *etag = encoding.etag(etag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brotli::Decompressor;
+    use flate2::read::GzDecoder;
+    use models::{Choice, Question};
+    use std::io::Read;
+
+    fn decompress(response: &APIGatewayResponse) -> String {
+        let compressed = STANDARD.decode(&response.body).unwrap();
+        let mut body = String::new();
+        match response.headers.get("Content-Encoding").map(String::as_str) {
+            Some("gzip") => GzDecoder::new(&compressed[..])
+                .read_to_string(&mut body)
+                .unwrap(),
+            Some("br") => Decompressor::new(&compressed[..], BROTLI_BUFFER_SIZE)
+                .read_to_string(&mut body)
+                .unwrap(),
+            encoding => panic!("Unexpected encoding {:?}", encoding),
+        };
+        body
+    }
+
+    fn questions_response() -> APIGatewayResponse {
+        let questions: Vec<Question> = (0..20)
+            .map(|i| Question {
+                id: Some(i),
+                question: format!("Which of these long winded answers is number {}?", i),
+                category: "Trivia".into(),
+                choices: (0..4)
+                    .map(|j| Choice {
+                        id: Some(i * 4 + j),
+                        title: format!("A rather long choice text that is repeated, number {}", j),
+                        correct: j == 0,
+                    })
+                    .collect(),
+                difficulty: Default::default(),
+                tags: vec![],
+                updated_at: None,
+            })
+            .collect();
+        APIGatewayResponse::new(200, Some(&questions)).unwrap()
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            Encoding::negotiate("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(Encoding::negotiate("gzip"), Some(Encoding::Gzip));
+        assert_eq!(
+            Encoding::negotiate("br;q=0.5, GZIP;q=0.8"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(Encoding::negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::negotiate("br;q=0, *"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("identity, deflate"), None);
+        assert_eq!(Encoding::negotiate("*;q=0"), None);
+        assert_eq!(Encoding::negotiate(""), None);
+    }
+
+    #[test]
+    fn test_compressed_bodies_round_trip() {
+        let original = questions_response();
+        let etag = original.etag();
+
+        for encoding in Encoding::ALL.iter() {
+            let mut response = questions_response().conditional(&Default::default());
+            CompressionPolicy::default().apply(Some(encoding.name()), &mut response);
+
+            assert!(response.is_base64_encoded);
+            assert!(response.body.len() < original.body.len());
+            assert_eq!(
+                response.headers.get("Content-Encoding"),
+                Some(&encoding.name().to_string())
+            );
+            assert_eq!(
+                response.headers.get("Vary"),
+                Some(&"Accept-Encoding".to_string())
+            );
+            assert_eq!(response.headers.get("ETag"), Some(&encoding.etag(&etag)));
+            assert_eq!(decompress(&response), original.body);
+        }
+    }
+
+    #[test]
+    fn test_small_bodies_and_unsupported_encodings_are_unchanged() {
+        let mut small = APIGatewayResponse::new(200, Some(&"short")).unwrap();
+        CompressionPolicy::default().apply(Some("gzip"), &mut small);
+        assert!(!small.is_base64_encoded);
+        assert!(!small.headers.contains_key("Vary"));
+
+        let mut identity = questions_response();
+        CompressionPolicy::default().apply(Some("identity"), &mut identity);
+        assert!(!identity.is_base64_encoded);
+        assert_eq!(identity.body, questions_response().body);
+        assert_eq!(
+            identity.headers.get("Vary"),
+            Some(&"Accept-Encoding".to_string())
+        );
+
+        let mut disabled = questions_response();
+        CompressionPolicy { min_size: None }.apply(Some("gzip"), &mut disabled);
+        assert!(!disabled.is_base64_encoded);
+    }
+
+    #[test]
+    fn test_encoded_etag() {
+        assert_eq!(Encoding::Gzip.etag("\"abc\""), "\"abc-gzip\"");
+        assert_eq!(Encoding::Brotli.etag("\"abc\""), "\"abc-br\"");
+    }
+}
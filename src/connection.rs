@@ -1,9 +1,273 @@
 extern crate postgres;
 
-use apigateway::APIErrorResponse;
+use log::{info, warn};
 use postgres::{Connection, TlsMode};
 use repositories::RepositoryError;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tls::{RustlsHandshake, SslMode, TlsSettings};
 
-pub fn connect_db_with_conn_string(conn_string: &str) -> Result<Connection, APIErrorResponse> {
-    Connection::connect(conn_string, TlsMode::None).map_err(|e| RepositoryError::from(e).into())
+/// Connections idle for longer than this are pinged before being reused.
+const VALIDATE_AFTER_IDLE: Duration = Duration::from_secs(1);
+
+/**
+ * Opens a connection, using TLS as configured by `sslmode` (see `TlsSettings`).
+ *
+ * Failures to establish the connection, including TLS handshake failures, are reported as
+ * `ConnectionError`s that mention the `sslmode` in use.
+ */
+pub fn connect(conn_string: &str) -> Result<Connection, RepositoryError> {
+    let (conn_string, settings) = TlsSettings::from_conn_string(conn_string)?;
+    let conn_string = conn_string.as_str();
+
+    let result = match settings.mode {
+        SslMode::Disable => Connection::connect(conn_string, TlsMode::None),
+        SslMode::Prefer => {
+            let handshake = RustlsHandshake::new(&settings)?;
+            Connection::connect(conn_string, TlsMode::Prefer(&handshake))
+        }
+        SslMode::Require | SslMode::VerifyFull => {
+            let handshake = RustlsHandshake::new(&settings)?;
+            Connection::connect(conn_string, TlsMode::Require(&handshake))
+        }
+    };
+
+    result.map_err(|e| match RepositoryError::from(e) {
+        RepositoryError::DatabaseError(code, message) => {
+            RepositoryError::DatabaseError(code, message)
+        }
+        e => RepositoryError::ConnectionError(format!(
+            "Failed to connect to database (sslmode={}): {}",
+            settings.mode, e
+        )),
+    })
+}
+
+struct CachedConnection {
+    conn: Rc<Connection>,
+    last_used: Instant,
+}
+
+/**
+ * Lazily opens a single connection and reuses it until it breaks.
+ *
+ * A lambda container handles one invocation at a time, so one connection per container is enough.
+ * The connection is opened on first use, pinged before reuse if it has been idle (e.g. while the
+ * container was frozen between invocations) and dropped after a connection or IO error, so that
+ * the next call reconnects. Only read-only calls are retried on a new connection (see
+ * `with_read_connection`).
+ */
+pub struct ConnectionManager {
+    conn_string: String,
+    cached: RefCell<Option<CachedConnection>>,
+}
+
+impl ConnectionManager {
+    pub fn new(conn_string: &str) -> ConnectionManager {
+        ConnectionManager {
+            conn_string: conn_string.to_string(),
+            cached: RefCell::new(None),
+        }
+    }
+
+    /// Returns the cached connection if it is still usable, otherwise opens a new one.
+    pub fn get(&self) -> Result<Rc<Connection>, RepositoryError> {
+        let mut cached = self.cached.borrow_mut();
+
+        let reusable = match *cached {
+            Some(ref c) if c.last_used.elapsed() < VALIDATE_AFTER_IDLE => true,
+            Some(ref c) => match c.conn.batch_execute("SELECT 1") {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!("Cached connection failed ping, reconnecting: {}", e);
+                    false
+                }
+            },
+            None => false,
+        };
+
+        if !reusable {
+            info!("Opening database connection.");
+            *cached = Some(CachedConnection {
+                conn: Rc::new(connect(&self.conn_string)?),
+                last_used: Instant::now(),
+            });
+        }
+
+        let c = cached.as_mut().unwrap();
+        c.last_used = Instant::now();
+        Ok(c.conn.clone())
+    }
+
+    /// Drops the cached connection so that the next call to `get` reconnects.
+    pub fn invalidate(&self) {
+        *self.cached.borrow_mut() = None;
+    }
+
+    /**
+     * Runs `f` with a connection.
+     *
+     * If `f` fails with a connection or IO error, the connection is invalidated. `f` is not run
+     * again, as a write may have been committed before the connection broke.
+     */
+    pub fn with_connection<T, F>(&self, f: F) -> Result<T, RepositoryError>
+    where
+        F: FnOnce(&Connection) -> Result<T, RepositoryError>,
+    {
+        let conn = self.get()?;
+        let result = f(&conn);
+
+        if is_connection_error(&result) {
+            warn!("Invalidating database connection after error.");
+            self.invalidate();
+        }
+        result
+    }
+
+    /**
+     * Runs the read-only `f` with a connection, like `with_connection`.
+     *
+     * If `f` fails with a connection or IO error, it is run once more on a new connection, as a
+     * connection cached by a frozen container may have been closed by the server without failing
+     * the ping. Only use it for calls that do not write.
+     */
+    pub fn with_read_connection<T, F>(&self, f: F) -> Result<T, RepositoryError>
+    where
+        F: Fn(&Connection) -> Result<T, RepositoryError>,
+    {
+        retry_once(|| self.get(), || self.invalidate(), |conn| f(conn))
+    }
+}
+
+fn is_connection_error<T>(result: &Result<T, RepositoryError>) -> bool {
+    matches!(
+        result,
+        Err(RepositoryError::ConnectionError(_)) | Err(RepositoryError::IOError(_))
+    )
+}
+
+/// Runs `f` with the connection returned by `get`, and again with a new one if it fails with a
+/// connection or IO error.
+fn retry_once<C, T>(
+    get: impl Fn() -> Result<C, RepositoryError>,
+    invalidate: impl Fn(),
+    f: impl Fn(&C) -> Result<T, RepositoryError>,
+) -> Result<T, RepositoryError> {
+    let result = f(&get()?);
+    if !is_connection_error(&result) {
+        return result;
+    }
+
+    warn!("Invalidating database connection after error, retrying.");
+    invalidate();
+    let result = f(&get()?);
+    if is_connection_error(&result) {
+        warn!("Invalidating database connection after error.");
+        invalidate();
+    }
+    result
+}
+
+thread_local! {
+    static SHARED_MANAGER: RefCell<Option<Rc<ConnectionManager>>> = const { RefCell::new(None) };
+}
+
+/**
+ * Returns the connection manager for `conn_string` that lives as long as the container.
+ *
+ * Lambda invocations are handled on the same thread, so warm invocations reuse the connection
+ * opened by a previous invocation.
+ */
+pub fn shared_connection_manager(conn_string: &str) -> Rc<ConnectionManager> {
+    SHARED_MANAGER.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        match *shared {
+            Some(ref manager) if manager.conn_string == conn_string => manager.clone(),
+            _ => {
+                let manager = Rc::new(ConnectionManager::new(conn_string));
+                *shared = Some(manager.clone());
+                manager
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    const UNREACHABLE: &str = "postgres://postgres@127.0.0.1:1/quizzicaldb_test";
+
+    #[test]
+    fn test_shared_connection_manager_is_reused() {
+        let first = shared_connection_manager(UNREACHABLE);
+        let second = shared_connection_manager(UNREACHABLE);
+        assert!(Rc::ptr_eq(&first, &second));
+
+        let other = shared_connection_manager("postgres://postgres@127.0.0.1:2/other");
+        assert!(!Rc::ptr_eq(&first, &other));
+    }
+
+    #[test]
+    fn test_connect_without_sslmode_prefers_tls() {
+        match connect(UNREACHABLE) {
+            Err(RepositoryError::ConnectionError(message)) => {
+                assert!(message.contains("sslmode=prefer"), "{}", message)
+            }
+            Err(e) => panic!("Expected ConnectionError, got {:?}", e),
+            Ok(_) => panic!("Expected ConnectionError"),
+        }
+    }
+
+    #[test]
+    fn test_failed_connect_is_not_cached() {
+        let manager = ConnectionManager::new(UNREACHABLE);
+
+        match manager.get() {
+            Err(RepositoryError::ConnectionError(_)) | Err(RepositoryError::IOError(_)) => {}
+            Err(e) => panic!("Expected connection error, got {:?}", e),
+            Ok(_) => panic!("Expected connection error"),
+        }
+        assert!(manager.cached.borrow().is_none());
+    }
+
+    #[test]
+    fn test_with_connection_retries_on_a_new_connection() {
+        let opened = Cell::new(0);
+        let invalidated = Cell::new(0);
+        let get = || {
+            opened.set(opened.get() + 1);
+            Ok(opened.get())
+        };
+        let invalidate = || invalidated.set(invalidated.get() + 1);
+
+        let result = retry_once(get, invalidate, |conn: &i32| match *conn {
+            1 => Err(RepositoryError::IOError("connection reset".into())),
+            conn => Ok(conn),
+        });
+        assert_eq!(
+            (result.unwrap(), opened.get(), invalidated.get()),
+            (2, 2, 1)
+        );
+
+        opened.set(0);
+        invalidated.set(0);
+        let result = retry_once(get, invalidate, |_: &i32| -> Result<(), RepositoryError> {
+            Err(RepositoryError::ConnectionError("closed".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!((opened.get(), invalidated.get()), (2, 2));
+
+        opened.set(0);
+        let result = retry_once(get, invalidate, |_: &i32| -> Result<(), RepositoryError> {
+            Err(RepositoryError::DatabaseError(
+                "23505".into(),
+                "duplicate".into(),
+            ))
+        });
+        assert!(result.is_err());
+        assert_eq!(opened.get(), 1);
+    }
 }
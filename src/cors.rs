@@ -0,0 +1,359 @@
+use apigateway::{APIErrorResponse, APIErrorType, APIGatewayEvent, APIGatewayResponse};
+use std::env;
+
+/// The cross-origin requests allowed by browsers.
+///
+/// Origins are matched exactly (ignoring case), `*` allows every origin and `https://*.example.com`
+/// allows every subdomain of `example.com` over `https`. When credentials are allowed, the
+/// request's origin is echoed instead of `*`, as browsers reject `*` for credentialed requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub expose_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// How long, in seconds, browsers may cache a preflight response.
+    pub max_age: Option<u64>,
+}
+
+/// Allows any origin without credentials, like the API always did.
+impl Default for CorsPolicy {
+    fn default() -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: vec!["*".into()],
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"]
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+            allowed_headers: vec![
+                "Authorization".into(),
+                "Content-Type".into(),
+                "X-Session-Token".into(),
+            ],
+            expose_headers: vec![],
+            allow_credentials: false,
+            max_age: Some(600),
+        }
+    }
+}
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+impl CorsPolicy {
+    /**
+     * Reads `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS`, `CORS_ALLOWED_HEADERS` and
+     * `CORS_EXPOSE_HEADERS` (comma separated), `CORS_ALLOW_CREDENTIALS` (`true`/`false`) and
+     * `CORS_MAX_AGE` (seconds). Unset variables keep their default.
+     *
+     * Allowing credentials for any origin (`*`) is rejected, as it would let every site make
+     * authenticated requests on behalf of the user.
+     */
+    pub fn from_env() -> Result<CorsPolicy, String> {
+        CorsPolicy::from_lookup(&|name| env::var(name).ok())
+    }
+
+    /// Reads the policy from the variables returned by `lookup`, so it can be tested without
+    /// changing the environment of the test process.
+    fn from_lookup(lookup: &dyn Fn(&str) -> Option<String>) -> Result<CorsPolicy, String> {
+        let mut policy = CorsPolicy::default();
+        if let Some(origins) = lookup("CORS_ALLOWED_ORIGINS") {
+            policy.allowed_origins = list(&origins);
+        }
+        if let Some(methods) = lookup("CORS_ALLOWED_METHODS") {
+            policy.allowed_methods = list(&methods.to_uppercase());
+        }
+        if let Some(headers) = lookup("CORS_ALLOWED_HEADERS") {
+            policy.allowed_headers = list(&headers);
+        }
+        if let Some(headers) = lookup("CORS_EXPOSE_HEADERS") {
+            policy.expose_headers = list(&headers);
+        }
+        if let Some(credentials) = lookup("CORS_ALLOW_CREDENTIALS") {
+            policy.allow_credentials = credentials
+                .parse()
+                .map_err(|_| "CORS_ALLOW_CREDENTIALS must be true or false".to_string())?;
+        }
+        if let Some(max_age) = lookup("CORS_MAX_AGE") {
+            policy.max_age = Some(
+                max_age
+                    .parse()
+                    .map_err(|_| "CORS_MAX_AGE must be a number of seconds".to_string())?,
+            );
+        }
+        if policy.allow_credentials && policy.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err(
+                "CORS_ALLOW_CREDENTIALS can not be true when CORS_ALLOWED_ORIGINS includes '*'"
+                    .to_string(),
+            );
+        }
+        Ok(policy)
+    }
+
+    fn origin_matches(pattern: &str, origin: &str) -> bool {
+        let (pattern, origin) = (pattern.to_lowercase(), origin.to_lowercase());
+        match pattern.find("*.") {
+            Some(index) => {
+                let (scheme, domain) = (&pattern[..index], &pattern[index + 1..]);
+                origin.len() > scheme.len() + domain.len()
+                    && origin.starts_with(scheme)
+                    && origin.ends_with(domain)
+                    && origin[scheme.len()..origin.len() - domain.len()]
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            }
+            None => pattern == origin,
+        }
+    }
+
+    /// The `Access-Control-Allow-Origin` value for a request from `origin`, if it is allowed.
+    pub fn allow_origin(&self, origin: Option<&str>) -> Option<String> {
+        let any = self.allowed_origins.iter().any(|pattern| pattern == "*");
+        match origin {
+            Some(origin) if self.allow_credentials || !any => self
+                .allowed_origins
+                .iter()
+                .any(|pattern| pattern == "*" || CorsPolicy::origin_matches(pattern, origin))
+                .then(|| origin.to_string()),
+            _ if any && !self.allow_credentials => Some("*".into()),
+            _ => None,
+        }
+    }
+
+    /// Adds the CORS headers for a request from `origin` to the response.
+    pub fn apply(&self, origin: Option<&str>, response: &mut APIGatewayResponse) {
+        let allowed = match self.allow_origin(origin) {
+            Some(allowed) => allowed,
+            None => return,
+        };
+        if allowed != "*" {
+            response.add_vary("Origin");
+        }
+        if self.allow_credentials {
+            response
+                .headers
+                .insert("Access-Control-Allow-Credentials".into(), "true".into());
+        }
+        if !self.expose_headers.is_empty() {
+            response.headers.insert(
+                "Access-Control-Expose-Headers".into(),
+                self.expose_headers.join(", "),
+            );
+        }
+        response
+            .headers
+            .insert("Access-Control-Allow-Origin".into(), allowed);
+    }
+
+    /**
+     * Answers `OPTIONS` requests with an `Access-Control-Request-Method` header.
+     *
+     * The response is returned before authorization, since browsers never send credentials
+     * with preflight requests. Other requests, including plain `OPTIONS` requests, return `None`.
+     */
+    pub fn preflight(
+        &self,
+        event: &APIGatewayEvent,
+    ) -> Option<Result<APIGatewayResponse, APIErrorResponse>> {
+        if !event.http_method.eq_ignore_ascii_case("OPTIONS") {
+            return None;
+        }
+        let method = event.header("Access-Control-Request-Method")?;
+
+        let forbidden = |detail: String| -> APIErrorResponse {
+            APIErrorType::Forbidden {
+                detail: Some(detail),
+            }
+            .into()
+        };
+
+        if self.allow_origin(event.header("Origin")).is_none() {
+            return Some(Err(forbidden(format!(
+                "Origin '{}' is not allowed",
+                event.header("Origin").unwrap_or("")
+            ))));
+        }
+        if !self
+            .allowed_methods
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(method))
+        {
+            return Some(Err(forbidden(format!(
+                "Method '{}' is not allowed",
+                method
+            ))));
+        }
+
+        let mut response = APIGatewayResponse::new::<()>(204, None).unwrap();
+        response.headers.insert(
+            "Access-Control-Allow-Methods".into(),
+            self.allowed_methods.join(", "),
+        );
+        if !self.allowed_headers.is_empty() {
+            response.headers.insert(
+                "Access-Control-Allow-Headers".into(),
+                self.allowed_headers.join(", "),
+            );
+        }
+        if let Some(max_age) = self.max_age {
+            response
+                .headers
+                .insert("Access-Control-Max-Age".into(), max_age.to_string());
+        }
+        Some(Ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn console_policy() -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: vec![
+                "https://console.quizzicalapp.ml".into(),
+                "https://*.preview.quizzicalapp.ml".into(),
+            ],
+            allow_credentials: true,
+            expose_headers: vec!["ETag".into()],
+            ..Default::default()
+        }
+    }
+
+    fn preflight_event(origin: &str, method: &str) -> APIGatewayEvent {
+        let mut headers = HashMap::new();
+        headers.insert("origin".to_string(), origin.to_string());
+        headers.insert(
+            "access-control-request-method".to_string(),
+            method.to_string(),
+        );
+        APIGatewayEvent {
+            http_method: "OPTIONS".into(),
+            path: "/questions".into(),
+            headers: Some(headers),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_policy_allows_any_origin() {
+        let policy = CorsPolicy::default();
+
+        assert_eq!(policy.allow_origin(None), Some("*".to_string()));
+        assert_eq!(
+            policy.allow_origin(Some("https://example.com")),
+            Some("*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_policy_from_variables() {
+        let policy = CorsPolicy::from_lookup(&|name| match name {
+            "CORS_ALLOWED_ORIGINS" => Some("https://console.quizzicalapp.ml".into()),
+            "CORS_ALLOW_CREDENTIALS" => Some("true".into()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(
+            policy.allowed_origins,
+            vec!["https://console.quizzicalapp.ml".to_string()]
+        );
+        assert!(policy.allow_credentials);
+
+        let any_origin_with_credentials = CorsPolicy::from_lookup(&|name| match name {
+            "CORS_ALLOWED_ORIGINS" => Some("https://console.quizzicalapp.ml, *".into()),
+            "CORS_ALLOW_CREDENTIALS" => Some("true".into()),
+            _ => None,
+        });
+        assert!(any_origin_with_credentials.is_err());
+
+        let default_origins_with_credentials = CorsPolicy::from_lookup(&|name| match name {
+            "CORS_ALLOW_CREDENTIALS" => Some("true".into()),
+            _ => None,
+        });
+        assert!(default_origins_with_credentials.is_err());
+    }
+
+    #[test]
+    fn test_origins_and_wildcard_subdomains() {
+        let policy = console_policy();
+        let allowed = |origin: &str| policy.allow_origin(Some(origin)).is_some();
+
+        assert!(allowed("https://console.quizzicalapp.ml"));
+        assert!(allowed("https://CONSOLE.quizzicalapp.ml"));
+        assert!(allowed("https://pr-12.preview.quizzicalapp.ml"));
+        assert!(!allowed("https://preview.quizzicalapp.ml"));
+        assert!(!allowed("http://pr-12.preview.quizzicalapp.ml"));
+        assert!(!allowed("https://evil.com/.preview.quizzicalapp.ml"));
+        assert!(!allowed("https://quizzicalapp.ml.evil.com"));
+        assert_eq!(policy.allow_origin(None), None);
+    }
+
+    #[test]
+    fn test_apply_echoes_origin_with_credentials() {
+        let policy = console_policy();
+        let mut response = APIGatewayResponse::new::<()>(200, None).unwrap();
+
+        policy.apply(Some("https://console.quizzicalapp.ml"), &mut response);
+
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Origin"),
+            Some(&"https://console.quizzicalapp.ml".to_string())
+        );
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Credentials"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(response.headers.get("Vary"), Some(&"Origin".to_string()));
+        assert_eq!(
+            response.headers.get("Access-Control-Expose-Headers"),
+            Some(&"ETag".to_string())
+        );
+
+        let mut rejected = APIGatewayResponse::new::<()>(200, None).unwrap();
+        policy.apply(Some("https://evil.com"), &mut rejected);
+        assert!(!rejected.headers.contains_key("Access-Control-Allow-Origin"));
+    }
+
+    #[test]
+    fn test_preflight() {
+        let policy = console_policy();
+
+        let response = policy
+            .preflight(&preflight_event("https://console.quizzicalapp.ml", "PUT"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status_code, 204);
+        assert_eq!(
+            response.headers.get("Access-Control-Allow-Methods"),
+            Some(&"GET, POST, PUT, PATCH, DELETE, OPTIONS".to_string())
+        );
+        assert_eq!(
+            response.headers.get("Access-Control-Max-Age"),
+            Some(&"600".to_string())
+        );
+
+        match policy.preflight(&preflight_event("https://evil.com", "PUT")) {
+            Some(Err(err)) => assert_eq!(err.status_code(), 403),
+            _ => panic!("Expected the origin to be rejected"),
+        }
+        match policy.preflight(&preflight_event("https://console.quizzicalapp.ml", "TRACE")) {
+            Some(Err(err)) => assert_eq!(err.status_code(), 403),
+            _ => panic!("Expected the method to be rejected"),
+        }
+
+        let get = APIGatewayEvent {
+            http_method: "GET".into(),
+            ..preflight_event("https://console.quizzicalapp.ml", "GET")
+        };
+        assert!(policy.preflight(&get).is_none());
+    }
+}
@@ -0,0 +1,203 @@
+extern crate base64;
+extern crate log;
+extern crate quizzical;
+extern crate simple_logger;
+extern crate tiny_http;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::{error, info};
+use quizzical::apigateway::*;
+use quizzical::auth::AuthConfig;
+use quizzical::compression::CompressionPolicy;
+use quizzical::connection::ConnectionManager;
+use quizzical::cors::CorsPolicy;
+use quizzical::handlers::api_router;
+use quizzical::in_memory::InMemoryStore;
+use quizzical::router::parse_query_string;
+use quizzical::validation::QuestionRules;
+use std::env;
+use std::error::Error;
+use std::rc::Rc;
+use tiny_http::{Header, Request, Response, Server};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:3000";
+
+/**
+ * Serves the lambda handlers over plain HTTP for local development.
+ *
+ * Requests are converted into `APIGatewayEvent`s and dispatched through `api_router`, so the
+ * handlers run exactly as they do behind API Gateway.
+ *
+ * If `CONN_STRING` is set, the handlers use the Postgres repositories. Otherwise an
+ * `InMemoryStore` is shared across requests and lost when the server stops.
+ * The listening address can be changed with `DEV_SERVER_ADDRESS`.
+ *
+ * Bearer tokens are checked like in the lambdas if the `JWT_*` variables are set (see `AuthConfig`),
+ * the `CORS_*` variables configure the `CorsPolicy` and `COMPRESSION_MIN_SIZE` configures the
+ * `CompressionPolicy`.
+ */
+fn main() -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_level(log::Level::Debug).unwrap();
+
+    let address = env::var("DEV_SERVER_ADDRESS").unwrap_or_else(|_| DEFAULT_ADDRESS.into());
+    let connections = env::var("CONN_STRING")
+        .ok()
+        .map(|conn_string| Rc::new(ConnectionManager::new(&conn_string)));
+    let store = Rc::new(InMemoryStore::new());
+    let auth = AuthConfig::from_env()
+        .map_err(|e| format!("{}", e))?
+        .map(Rc::new);
+    let cors = CorsPolicy::from_env()?;
+    let compression = CompressionPolicy::from_env()?;
+    let question_rules = QuestionRules::from_env()?;
+
+    let server = Server::http(&address).map_err(|e| format!("{}", e))?;
+    info!(
+        "Listening on http://{} using {}.",
+        address,
+        match connections {
+            Some(_) => "CONN_STRING",
+            None => "an in-memory store",
+        }
+    );
+
+    let router = api_router();
+    for mut request in server.incoming_requests() {
+        let event = to_event(&mut request);
+        info!("APIGatewayEvent: {}", event);
+
+        let config = match connections {
+            Some(ref connections) => Config::with_connection_manager(connections.clone()),
+            None => Config::new(store.clone(), store.clone(), store.clone(), store.clone()),
+        }
+        .with_auth(auth.clone())
+        .with_cors(cors.clone())
+        .with_compression(compression.clone())
+        .with_question_rules(question_rules.clone());
+
+        let permission = router.permission(&event);
+        let response = handle_event(event, config, permission, &|event, config| {
+            router.handle(event, config)
+        })?;
+
+        if let Err(e) = request.respond(to_http_response(response)) {
+            error!("Failed to write response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn to_event(request: &mut Request) -> APIGatewayEvent {
+    let mut body = String::new();
+    let body = match request.as_reader().read_to_string(&mut body) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(body),
+    };
+
+    let headers = request
+        .headers()
+        .iter()
+        .map(|header| (header.field.to_string(), header.value.to_string()))
+        .collect();
+
+    APIGatewayEvent {
+        headers: Some(headers),
+        ..event_from_parts(&request.method().to_string(), request.url(), body)
+    }
+}
+
+fn event_from_parts(method: &str, url: &str, body: Option<String>) -> APIGatewayEvent {
+    let (path, query_string) = match url.find('?') {
+        Some(index) => (&url[..index], Some(&url[index + 1..])),
+        None => (url, None),
+    };
+
+    let multi_value_query = query_string
+        .map(parse_query_string)
+        .filter(|query| !query.is_empty());
+
+    // Like API Gateway, `query` keeps only the last value of a repeated query parameter.
+    let query = multi_value_query.as_ref().map(|query| {
+        query
+            .iter()
+            .filter_map(|(name, values)| values.last().map(|value| (name.clone(), value.clone())))
+            .collect()
+    });
+
+    APIGatewayEvent {
+        http_method: method.to_uppercase(),
+        path: path.to_string(),
+        query,
+        multi_value_query,
+        body,
+        ..Default::default()
+    }
+}
+
+fn to_http_response(response: APIGatewayResponse) -> Response<std::io::Cursor<Vec<u8>>> {
+    // Compressed bodies are base64 encoded, like API Gateway expects them.
+    let body = match response.is_base64_encoded {
+        true => STANDARD.decode(&response.body).unwrap_or_default(),
+        false => response.body.into_bytes(),
+    };
+    let mut http_response = Response::from_data(body).with_status_code(response.status_code);
+
+    let mut has_content_type = false;
+    for (name, value) in response.headers.iter() {
+        has_content_type |= name.eq_ignore_ascii_case("Content-Type");
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            http_response.add_header(header);
+        }
+    }
+
+    if !has_content_type {
+        http_response.add_header(
+            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+        );
+    }
+    http_response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_from_parts_splits_query() {
+        let event = event_from_parts(
+            "get",
+            "/questions?category=General+Knowledge&page=2&size=%31%30",
+            None,
+        );
+
+        assert_eq!(event.http_method, "GET");
+        assert_eq!(event.path, "/questions");
+        assert_eq!(
+            event.get_query::<String>("category"),
+            Some("General Knowledge".to_string())
+        );
+        assert_eq!(event.get_query::<i64>("page"), Some(2));
+        assert_eq!(event.get_query::<i64>("size"), Some(10));
+    }
+
+    #[test]
+    fn test_event_from_parts_keeps_repeated_parameters() {
+        let event = event_from_parts("GET", "/questions?tag=space&tag=cold+war", None);
+
+        assert_eq!(
+            event.get_query::<String>("tag"),
+            Some("cold war".to_string())
+        );
+        assert_eq!(event.get_query_values("tag"), vec!["space", "cold war"]);
+    }
+
+    #[test]
+    fn test_event_from_parts_without_query() {
+        let event = event_from_parts("POST", "/questions", Some("{}".into()));
+
+        assert_eq!(event.path, "/questions");
+        assert!(event.query.is_none());
+        assert_eq!(event.body, Some("{}".to_string()));
+    }
+}
@@ -0,0 +1,530 @@
+use apigateway::{APIGatewayEvent, APIGatewayResponse, RequestContext, RequestIdentity};
+use http::StatusCode;
+use router::{parse_query_string, percent_decode};
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{from_value, Value};
+use std::collections::HashMap;
+
+/* #region HTTP API (payload format 2.0) */
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HttpApiEvent {
+    pub version: String,
+    /// `<method> <path template>` e.g. `GET /questions/{id}`, or `$default`.
+    #[serde(rename = "routeKey")]
+    pub route_key: String,
+    #[serde(rename = "rawPath")]
+    pub raw_path: String,
+    #[serde(rename = "rawQueryString", default)]
+    pub raw_query_string: String,
+    pub cookies: Option<Vec<String>>,
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(rename = "queryStringParameters")]
+    pub query: Option<HashMap<String, String>>,
+    #[serde(rename = "pathParameters")]
+    pub path_parameters: Option<HashMap<String, String>>,
+    #[serde(rename = "requestContext")]
+    pub request_context: HttpApiRequestContext,
+    pub body: Option<String>,
+    #[serde(rename = "isBase64Encoded", default)]
+    pub is_base64_encoded: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HttpApiRequestContext {
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    pub stage: Option<String>,
+    pub http: HttpApiHttp,
+    pub authorizer: Option<HttpApiAuthorizer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HttpApiHttp {
+    pub method: String,
+    pub path: Option<String>,
+    #[serde(rename = "sourceIp")]
+    pub source_ip: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HttpApiAuthorizer {
+    pub jwt: Option<HttpApiJwt>,
+    pub lambda: Option<HashMap<String, Value>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HttpApiJwt {
+    pub claims: Option<HashMap<String, Value>>,
+}
+
+impl From<HttpApiEvent> for APIGatewayEvent {
+    fn from(event: HttpApiEvent) -> APIGatewayEvent {
+        let context = event.request_context;
+
+        // `rawPath` starts with the stage, unless the API uses the `$default` stage.
+        let path = match context.stage {
+            Some(ref stage) if stage != "$default" => {
+                let prefix = format!("/{}", stage);
+                match event.raw_path.strip_prefix(&prefix) {
+                    Some(path) if path.is_empty() || path.starts_with('/') => path.to_string(),
+                    _ => event.raw_path.clone(),
+                }
+            }
+            _ => event.raw_path.clone(),
+        };
+
+        let resource = event
+            .route_key
+            .split_once(' ')
+            .map(|(_, template)| template.to_string());
+
+        let mut headers = event.headers.unwrap_or_default();
+        if let Some(cookies) = event.cookies.filter(|cookies| !cookies.is_empty()) {
+            headers.insert("cookie".into(), cookies.join("; "));
+        }
+
+        // A Lambda authorizer returns its context as is; a JWT authorizer identifies the
+        // principal by the `sub` claim.
+        let authorizer = context.authorizer.map(|authorizer| {
+            let mut values = authorizer.lambda.unwrap_or_default();
+            let subject = authorizer
+                .jwt
+                .and_then(|jwt| jwt.claims)
+                .and_then(|claims| claims.get("sub").cloned());
+            if let Some(subject) = subject {
+                values.entry("principalId".into()).or_insert(subject);
+            }
+            values
+        });
+
+        APIGatewayEvent {
+            http_method: context.http.method.to_uppercase(),
+            path,
+            resource,
+            query: event.query,
+            // `queryStringParameters` joins the values of a repeated parameter with commas.
+            multi_value_query: Some(parse_query_string(&event.raw_query_string))
+                .filter(|query| !query.is_empty()),
+            path_parameters: event.path_parameters,
+            headers: Some(headers),
+            request_context: Some(RequestContext {
+                request_id: context.request_id,
+                identity: Some(RequestIdentity {
+                    source_ip: context.http.source_ip,
+                }),
+                authorizer,
+            }),
+            body: event.body,
+            is_base64_encoded: event.is_base64_encoded,
+        }
+    }
+}
+
+/* #region Application Load Balancer */
+
+/// Query parameters and headers are repeated in the `multiValue` fields, instead of the
+/// single value ones, if multi value headers are enabled on the target group.
+#[derive(Debug, Default, Deserialize)]
+pub struct AlbEvent {
+    #[serde(rename = "httpMethod")]
+    pub http_method: String,
+    pub path: String,
+    #[serde(rename = "queryStringParameters")]
+    pub query: Option<HashMap<String, String>>,
+    #[serde(rename = "multiValueQueryStringParameters")]
+    pub multi_value_query: Option<HashMap<String, Vec<String>>>,
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(rename = "multiValueHeaders")]
+    pub multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    pub body: Option<String>,
+    #[serde(rename = "isBase64Encoded", default)]
+    pub is_base64_encoded: bool,
+}
+
+/// The load balancer does not decode query parameters.
+fn decode_query_value(value: &str) -> String {
+    percent_decode(&value.replace('+', " "))
+}
+
+impl From<AlbEvent> for APIGatewayEvent {
+    fn from(event: AlbEvent) -> APIGatewayEvent {
+        let multi_value_query: Option<HashMap<String, Vec<String>>> =
+            event.multi_value_query.map(|query| {
+                query
+                    .iter()
+                    .map(|(name, values)| {
+                        (
+                            decode_query_value(name),
+                            values
+                                .iter()
+                                .map(|value| decode_query_value(value))
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            });
+
+        // Like API Gateway, `query` keeps only the last value of a repeated query parameter.
+        let query: Option<HashMap<String, String>> = match multi_value_query {
+            Some(ref query) => Some(
+                query
+                    .iter()
+                    .filter_map(|(name, values)| {
+                        values.last().map(|value| (name.clone(), value.clone()))
+                    })
+                    .collect(),
+            ),
+            None => event.query.map(|query| {
+                query
+                    .iter()
+                    .map(|(name, value)| (decode_query_value(name), decode_query_value(value)))
+                    .collect()
+            }),
+        };
+
+        let headers: HashMap<String, String> = match event.multi_value_headers {
+            Some(headers) => headers
+                .into_iter()
+                .map(|(name, values)| (name, values.join(",")))
+                .collect(),
+            None => event.headers.unwrap_or_default(),
+        };
+
+        let source_ip = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("x-forwarded-for"))
+            .and_then(|(_, value)| value.split(',').next())
+            .map(|ip| ip.trim().to_string());
+        let request_id = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("x-amzn-trace-id"))
+            .map(|(_, value)| value.clone());
+
+        APIGatewayEvent {
+            http_method: event.http_method.to_uppercase(),
+            path: event.path,
+            query: query.filter(|query| !query.is_empty()),
+            multi_value_query: multi_value_query.filter(|query| !query.is_empty()),
+            headers: Some(headers),
+            request_context: Some(RequestContext {
+                request_id,
+                identity: Some(RequestIdentity { source_ip }),
+                authorizer: None,
+            }),
+            body: event.body.filter(|body| !body.is_empty()),
+            is_base64_encoded: event.is_base64_encoded,
+            ..Default::default()
+        }
+    }
+}
+
+/* #region LambdaRequest */
+
+/**
+ * An HTTP request delivered to a lambda by a REST API, an HTTP API or a load balancer.
+ *
+ * Handlers only deal with `APIGatewayEvent`s; `into_event` normalizes the request and returns
+ * the format the response must be written in.
+ */
+#[derive(Debug)]
+pub enum LambdaRequest {
+    RestApi(APIGatewayEvent),
+    HttpApi(HttpApiEvent),
+    Alb(AlbEvent),
+}
+
+impl<'de> Deserialize<'de> for LambdaRequest {
+    fn deserialize<D>(deserializer: D) -> Result<LambdaRequest, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+
+        let is_http_api = value.get("version").and_then(Value::as_str) == Some("2.0");
+        let is_alb = value
+            .get("requestContext")
+            .and_then(|context| context.get("elb"))
+            .is_some();
+
+        match (is_http_api, is_alb) {
+            (true, _) => from_value(value).map(LambdaRequest::HttpApi),
+            (_, true) => from_value(value).map(LambdaRequest::Alb),
+            _ => from_value(value).map(LambdaRequest::RestApi),
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+impl From<APIGatewayEvent> for LambdaRequest {
+    fn from(event: APIGatewayEvent) -> LambdaRequest {
+        LambdaRequest::RestApi(event)
+    }
+}
+
+impl LambdaRequest {
+    pub fn into_event(self) -> (APIGatewayEvent, ResponseFormat) {
+        match self {
+            LambdaRequest::RestApi(event) => (event, ResponseFormat::RestApi),
+            LambdaRequest::HttpApi(event) => (event.into(), ResponseFormat::HttpApi),
+            LambdaRequest::Alb(event) => {
+                let multi_value_headers = event.multi_value_headers.is_some();
+                (
+                    event.into(),
+                    ResponseFormat::Alb {
+                        multi_value_headers,
+                    },
+                )
+            }
+        }
+    }
+}
+
+/* #region LambdaResponse */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseFormat {
+    RestApi,
+    HttpApi,
+    /// The load balancer expects `multiValueHeaders` in the response if it sent them.
+    Alb {
+        multi_value_headers: bool,
+    },
+}
+
+/// The union of the response fields of REST APIs, HTTP APIs and load balancers.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LambdaResponse {
+    #[serde(rename = "statusCode")]
+    pub status_code: u16,
+    #[serde(rename = "statusDescription", skip_serializing_if = "Option::is_none")]
+    pub status_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(rename = "multiValueHeaders", skip_serializing_if = "Option::is_none")]
+    pub multi_value_headers: Option<HashMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cookies: Option<Vec<String>>,
+    pub body: String,
+    #[serde(rename = "isBase64Encoded", skip_serializing_if = "Option::is_none")]
+    pub is_base64_encoded: Option<bool>,
+}
+
+impl ResponseFormat {
+    pub fn format(self, response: APIGatewayResponse) -> LambdaResponse {
+        let APIGatewayResponse {
+            status_code,
+            mut headers,
+            body,
+            is_base64_encoded,
+        } = response;
+
+        match self {
+            ResponseFormat::RestApi => LambdaResponse {
+                status_code,
+                headers: Some(headers),
+                body,
+                is_base64_encoded: is_base64_encoded.then_some(true),
+                ..Default::default()
+            },
+            ResponseFormat::HttpApi => {
+                // HTTP APIs ignore `Set-Cookie` headers, cookies have their own field.
+                let cookie_header = headers
+                    .keys()
+                    .find(|name| name.eq_ignore_ascii_case("Set-Cookie"))
+                    .cloned();
+                let cookies = cookie_header
+                    .and_then(|name| headers.remove(&name))
+                    .map(|cookie| vec![cookie]);
+
+                LambdaResponse {
+                    status_code,
+                    headers: Some(headers),
+                    cookies,
+                    body,
+                    is_base64_encoded: Some(is_base64_encoded),
+                    ..Default::default()
+                }
+            }
+            ResponseFormat::Alb {
+                multi_value_headers,
+            } => {
+                let status_description = Some(match StatusCode::from_u16(status_code) {
+                    Ok(status) => format!(
+                        "{} {}",
+                        status_code,
+                        status.canonical_reason().unwrap_or("Unknown")
+                    ),
+                    Err(_) => format!("{}", status_code),
+                });
+
+                let (headers, multi_value_headers) = match multi_value_headers {
+                    true => (
+                        None,
+                        Some(
+                            headers
+                                .into_iter()
+                                .map(|(name, value)| (name, vec![value]))
+                                .collect(),
+                        ),
+                    ),
+                    false => (Some(headers), None),
+                };
+
+                LambdaResponse {
+                    status_code,
+                    status_description,
+                    headers,
+                    multi_value_headers,
+                    body,
+                    is_base64_encoded: Some(is_base64_encoded),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_str, json, to_value};
+
+    const HTTP_API_EVENT: &str = r#"{
+        "version": "2.0",
+        "routeKey": "GET /questions/{id}",
+        "rawPath": "/prod/questions/42",
+        "rawQueryString": "tag=a&tag=b%20c",
+        "cookies": ["theme=dark", "lang=en"],
+        "headers": {"accept": "application/json"},
+        "queryStringParameters": {"tag": "a,b c"},
+        "pathParameters": {"id": "42"},
+        "requestContext": {
+            "requestId": "JKJaXmPLvHcESHA=",
+            "stage": "prod",
+            "http": {"method": "GET", "path": "/prod/questions/42", "sourceIp": "203.0.113.7"},
+            "authorizer": {"jwt": {"claims": {"sub": "editor@example.com"}, "scopes": null}}
+        },
+        "isBase64Encoded": false
+    }"#;
+
+    const ALB_EVENT: &str = r#"{
+        "requestContext": {
+            "elb": {"targetGroupArn": "arn:aws:elasticloadbalancing:us-east-1:123456789012:targetgroup/quizzical/6d0ecf831eec9f09"}
+        },
+        "httpMethod": "GET",
+        "path": "/questions",
+        "multiValueQueryStringParameters": {"category": ["General%20Knowledge"], "page": ["1", "2"]},
+        "multiValueHeaders": {
+            "accept": ["application/json"],
+            "x-forwarded-for": ["203.0.113.7, 10.0.0.1"]
+        },
+        "body": "",
+        "isBase64Encoded": false
+    }"#;
+
+    fn response() -> APIGatewayResponse {
+        let mut response = APIGatewayResponse::new(404, Some(&json!({"errors": []}))).unwrap();
+        response
+            .headers
+            .insert("Content-Type".into(), "application/json".into());
+        response
+    }
+
+    #[test]
+    fn test_rest_api_event_is_unchanged() {
+        let request: LambdaRequest =
+            from_str(r#"{"httpMethod": "GET", "path": "/categories"}"#).unwrap();
+
+        let (event, format) = request.into_event();
+        assert_eq!(format, ResponseFormat::RestApi);
+        assert_eq!(event.path, "/categories");
+
+        let response = to_value(format.format(response())).unwrap();
+        assert_eq!(response["statusCode"], 404);
+        assert!(response.get("isBase64Encoded").is_none());
+        assert!(response.get("statusDescription").is_none());
+    }
+
+    #[test]
+    fn test_http_api_event_is_normalized() {
+        let request: LambdaRequest = from_str(HTTP_API_EVENT).unwrap();
+
+        let (event, format) = request.into_event();
+        assert_eq!(format, ResponseFormat::HttpApi);
+        assert_eq!(event.http_method, "GET");
+        assert_eq!(event.path, "/questions/42");
+        assert_eq!(event.resource, Some("/questions/{id}".to_string()));
+        assert_eq!(event.get_path_param::<i64>("id"), Some(42));
+        assert_eq!(event.get_query::<String>("tag"), Some("a,b c".to_string()));
+        assert_eq!(event.get_query_values("tag"), vec!["a", "b c"]);
+        assert_eq!(event.header("Cookie"), Some("theme=dark; lang=en"));
+        assert_eq!(event.request_id(), Some("JKJaXmPLvHcESHA="));
+        assert_eq!(event.source_ip(), Some("203.0.113.7"));
+        assert_eq!(event.principal_id(), Some("editor@example.com"));
+    }
+
+    #[test]
+    fn test_http_api_response_moves_cookies() {
+        let mut response = response();
+        response
+            .headers
+            .insert("Set-Cookie".into(), "session=1; HttpOnly".into());
+
+        let response = to_value(ResponseFormat::HttpApi.format(response)).unwrap();
+        assert_eq!(response["cookies"], json!(["session=1; HttpOnly"]));
+        assert!(response["headers"].get("Set-Cookie").is_none());
+        assert_eq!(response["isBase64Encoded"], false);
+    }
+
+    #[test]
+    fn test_alb_event_is_normalized() {
+        let request: LambdaRequest = from_str(ALB_EVENT).unwrap();
+
+        let (event, format) = request.into_event();
+        assert_eq!(
+            format,
+            ResponseFormat::Alb {
+                multi_value_headers: true
+            }
+        );
+        assert_eq!(event.path, "/questions");
+        assert_eq!(
+            event.get_query::<String>("category"),
+            Some("General Knowledge".to_string())
+        );
+        assert_eq!(event.get_query::<i64>("page"), Some(2));
+        assert_eq!(event.get_query_values("page"), vec!["1", "2"]);
+        assert_eq!(event.header("Accept"), Some("application/json"));
+        assert_eq!(event.source_ip(), Some("203.0.113.7"));
+        assert_eq!(event.body, None);
+    }
+
+    #[test]
+    fn test_alb_response_shape() {
+        let single = to_value(
+            ResponseFormat::Alb {
+                multi_value_headers: false,
+            }
+            .format(response()),
+        )
+        .unwrap();
+        assert_eq!(single["statusDescription"], "404 Not Found");
+        assert_eq!(single["headers"]["Content-Type"], "application/json");
+        assert_eq!(single["isBase64Encoded"], false);
+
+        let multi = to_value(
+            ResponseFormat::Alb {
+                multi_value_headers: true,
+            }
+            .format(response()),
+        )
+        .unwrap();
+        assert!(multi.get("headers").is_none());
+        assert_eq!(
+            multi["multiValueHeaders"]["Content-Type"],
+            json!(["application/json"])
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use apigateway::*;
+use handlers::PUBLIC_CACHE_CONTROL;
+
+pub fn categories_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let categories = config.categories.list_categories()?;
+    let last_modified = categories
+        .categories
+        .iter()
+        .filter_map(|category| category.updated_at)
+        .max();
+    let api_response = APIGatewayResponse::new(200, Some(&categories))
+        .unwrap()
+        .with_cache_control(PUBLIC_CACHE_CONTROL)
+        .with_last_modified(last_modified)
+        .conditional(&event);
+
+    Ok(api_response)
+}
+
+/// Lists every category, including inactive ones, for administrators.
+pub fn category_summaries_handler(
+    _event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let summaries = config.categories.list_category_summaries()?;
+    let api_response = APIGatewayResponse::new(200, Some(&summaries)).unwrap();
+
+    Ok(api_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::in_memory_config;
+    use models::{Categories, Category, CategorySummaries};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_categories_returns_200_with_list() {
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: None,
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        let title = "Science".to_string();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new(title.clone()), Some(true));
+        let _ = config.categories.save_category(&Category::new("History"));
+
+        match categories_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let categories: Categories = resp.parse().unwrap();
+                assert_eq!(categories.categories.len(), 1);
+
+                assert_eq!(
+                    1,
+                    categories
+                        .categories
+                        .iter()
+                        .filter(|c| c.title == title)
+                        .count()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_categories_include_question_counts() {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Science"), Some(true));
+
+        match categories_handler(APIGatewayEvent::default(), config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                let json: serde_json::Value = resp.parse().unwrap();
+                let category = &json["categories"][0];
+
+                assert_eq!(category["title"], "Science");
+                assert_eq!(category["question_count"], 0);
+                assert!(category.get("description").is_none());
+                assert!(category["created_at"].is_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_categories_are_cached_until_modified() {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Science"), Some(true));
+
+        let resp = categories_handler(APIGatewayEvent::default(), config.clone()).unwrap();
+        let etag = resp.headers.get("ETag").unwrap().clone();
+        assert_eq!(
+            resp.headers.get("Cache-Control"),
+            Some(&PUBLIC_CACHE_CONTROL.to_string())
+        );
+        assert!(resp.headers.contains_key("Last-Modified"));
+
+        let mut headers = HashMap::new();
+        headers.insert("If-None-Match".to_string(), etag.clone());
+        let conditional = || APIGatewayEvent {
+            headers: Some(headers.clone()),
+            ..Default::default()
+        };
+
+        let resp = categories_handler(conditional(), config.clone()).unwrap();
+        assert_eq!(resp.status_code, 304);
+        assert!(resp.body.is_empty());
+
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("History"), Some(true));
+        let resp = categories_handler(conditional(), config).unwrap();
+        assert_eq!(resp.status_code, 200);
+        assert_ne!(resp.headers.get("ETag"), Some(&etag));
+    }
+
+    #[test]
+    fn test_category_summaries_include_inactive_categories() {
+        let event = APIGatewayEvent {
+            path: "/admin/categories".into(),
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Science"), Some(true));
+        let _ = config.categories.save_category(&Category::new("History"));
+
+        match category_summaries_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let summaries: CategorySummaries = resp.parse().unwrap();
+                let flags: Vec<(String, bool)> = summaries
+                    .categories
+                    .into_iter()
+                    .map(|c| (c.title, c.active))
+                    .collect();
+                assert_eq!(
+                    flags,
+                    vec![
+                        ("History".to_string(), false),
+                        ("Science".to_string(), true)
+                    ]
+                );
+            }
+        }
+    }
+}
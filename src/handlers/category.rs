@@ -0,0 +1,329 @@
+use apigateway::{APIErrorType::*, *};
+use models::{Category, CategorySummary};
+use repositories::{RepositoryError, SaveCategoryStatus};
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct NewCategory {
+    title: String,
+    #[serde(default)]
+    active: bool,
+    description: Option<String>,
+    icon_url: Option<String>,
+}
+
+fn category_name(event: &APIGatewayEvent) -> Result<String, APIErrorResponse> {
+    match event.get_path_param("name") {
+        Some(name) => Ok(name),
+        None => Err(QueryParameterError {
+            parameter: "name".into(),
+            detail: Some("category name required in path".into()),
+        }
+        .into()),
+    }
+}
+
+/// Titles are checked against the category rules of `QuestionRules`, and saved trimmed.
+fn category_title(title: Option<&str>, config: &Config) -> Result<Category, APIErrorResponse> {
+    match title {
+        Some(title) => {
+            config.question_rules.validate_category(title)?;
+            Ok(Category::new(title.trim()))
+        }
+        None => Err(BodyParameterError {
+            pointer: "/title".into(),
+            detail: Some("Expected {\"title\": \"<non-empty name>\" }".into()),
+        }
+        .into()),
+    }
+}
+
+fn category_not_found(name: &str) -> APIErrorResponse {
+    NotFound {
+        detail: Some(format!("Category '{}' does not exist", name)),
+    }
+    .into()
+}
+
+fn category_exists(name: &str) -> APIErrorResponse {
+    Conflict {
+        detail: Some(format!("Category '{}' already exists", name)),
+    }
+    .into()
+}
+
+pub fn new_category_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let new_category = event.parse::<NewCategory>()?;
+    let category = Category {
+        description: new_category.as_ref().and_then(|c| c.description.clone()),
+        icon_url: new_category.as_ref().and_then(|c| c.icon_url.clone()),
+        ..category_title(new_category.as_ref().map(|c| c.title.as_str()), &config)?
+    };
+    let active = new_category.is_some_and(|c| c.active);
+
+    match config.categories.save_category(&category)? {
+        SaveCategoryStatus::Exists => return Err(category_exists(&category.title)),
+        SaveCategoryStatus::Created => {}
+    }
+    if active {
+        config
+            .categories
+            .set_category_active(&category.title, true)?;
+    }
+
+    let summary = CategorySummary {
+        title: category.title,
+        active,
+        question_count: 0,
+    };
+    Ok(APIGatewayResponse::new(201, Some(&summary)).unwrap())
+}
+
+pub fn rename_category_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let name = category_name(&event)?;
+    let renamed = event.parse::<Category>()?;
+    let category = category_title(renamed.as_ref().map(|c| c.title.as_str()), &config)?;
+
+    match config.categories.rename_category(&name, &category.title) {
+        Ok(true) => Ok(APIGatewayResponse::new(200, Some(&category)).unwrap()),
+        Ok(false) => Err(category_not_found(&name)),
+        Err(RepositoryError::DatabaseError(ref code, _)) if code == "23505" => {
+            Err(category_exists(&category.title))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/**
+ * Deletes a category that has no questions or quiz sessions.
+ *
+ * With `?cascade=true`, the questions, the quiz sessions and the leaderboard scores of the category
+ * are deleted along with it. Otherwise categories that are still in use are a conflict.
+ */
+pub fn delete_category_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let name = category_name(&event)?;
+    let cascade = match event.get_query::<String>("cascade") {
+        Some(cascade) => cascade.parse::<bool>().map_err(|_| {
+            APIErrorResponse::from(QueryParameterError {
+                parameter: "cascade".into(),
+                detail: Some("Expected true or false".into()),
+            })
+        })?,
+        None => false,
+    };
+
+    match config.categories.delete_category(&name, cascade) {
+        Ok(true) => Ok(APIGatewayResponse::new::<Category>(204, None).unwrap()),
+        Ok(false) => Err(category_not_found(&name)),
+        Err(RepositoryError::DatabaseError(ref code, _)) if code == "23503" && !cascade => {
+            Err(Conflict {
+                detail: Some(format!(
+                    "Category '{}' has questions or quiz sessions. Delete it with ?cascade=true to delete them and its scores as well",
+                    name
+                )),
+            }
+            .into())
+        }
+        Err(RepositoryError::DatabaseError(ref code, _)) if code == "23503" => Err(Conflict {
+            detail: Some(format!(
+                "Questions of category '{}' were played in quiz sessions of other categories",
+                name
+            )),
+        }
+        .into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::{assert_pointers_resolve, in_memory_config};
+    use http::StatusCode;
+    use models::Question;
+    use std::collections::HashMap;
+
+    fn event(name: Option<&str>, body: Option<&str>) -> APIGatewayEvent {
+        APIGatewayEvent {
+            path: "/categories".into(),
+            path_parameters: name.map(|name| {
+                let mut path_parameters = HashMap::new();
+                path_parameters.insert("name".to_string(), name.to_string());
+                path_parameters
+            }),
+            body: body.map(|body| body.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_new_category() {
+        let config = in_memory_config();
+
+        match new_category_handler(
+            event(None, Some(r#"{"title": "Science", "active": true}"#)),
+            config,
+        ) {
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::CREATED);
+                let summary: CategorySummary = resp.parse().unwrap();
+                assert_eq!(summary.title, "Science");
+                assert!(summary.active);
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_existing_category_returns_409() {
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Science"));
+
+        match new_category_handler(event(None, Some(r#"{"title": "Science"}"#)), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::CONFLICT),
+        }
+    }
+
+    #[test]
+    fn test_blank_title_returns_400() {
+        let config = in_memory_config();
+
+        match new_category_handler(event(None, Some(r#"{"title": "  "}"#)), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_titles_are_checked_and_trimmed() {
+        let config = in_memory_config();
+        let shared = config.clone();
+
+        match new_category_handler(
+            event(None, Some(r#"{"title": "@science"}"#)),
+            config.clone(),
+        ) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+
+        match new_category_handler(event(None, Some(r#"{"title": " Science "}"#)), config) {
+            Ok(resp) => {
+                let summary: CategorySummary = resp.parse().unwrap();
+                assert_eq!(summary.title, "Science");
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+
+        match rename_category_handler(
+            event(Some("Science"), Some(r#"{"title": "Physics!"}"#)),
+            shared,
+        ) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_malformed_body_points_at_the_field() {
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Science"));
+        let body = r#"{"title": "Physics", "active": "yes"}"#;
+
+        match new_category_handler(event(None, Some(body)), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+                assert_pointers_resolve(body, &err);
+            }
+        }
+
+        let body = r#"{"title": 42}"#;
+        match rename_category_handler(event(Some("Science"), Some(body)), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+                assert_pointers_resolve(body, &err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rename_category() {
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Science"));
+        let _ = config.categories.save_category(&Category::new("History"));
+        let shared = config.clone();
+
+        match rename_category_handler(
+            event(Some("Science"), Some(r#"{"title": "Physics"}"#)),
+            shared,
+        ) {
+            Ok(resp) => assert_eq!(resp.status_code, StatusCode::OK),
+            Err(e) => panic!("{:?}", e),
+        }
+
+        match rename_category_handler(
+            event(Some("Physics"), Some(r#"{"title": "History"}"#)),
+            config,
+        ) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::CONFLICT),
+        }
+    }
+
+    #[test]
+    fn test_delete_category_in_use_requires_cascade() {
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Science"));
+        let question: Question = serde_json::from_str(
+            r#"{"question": "Why?", "category": "Science", "choices": [
+                {"title": "Because", "correct": true}, {"title": "Why not", "correct": false}
+            ]}"#,
+        )
+        .unwrap();
+        let _ = config.questions.save_question(&question).unwrap();
+        let cascade = |value: &str| {
+            let mut query = HashMap::new();
+            query.insert("cascade".to_string(), value.to_string());
+            APIGatewayEvent {
+                query: Some(query),
+                ..event(Some("Science"), None)
+            }
+        };
+
+        match delete_category_handler(event(Some("Science"), None), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::CONFLICT),
+        }
+        match delete_category_handler(cascade("yes"), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+        match delete_category_handler(cascade("true"), config) {
+            Ok(resp) => assert_eq!(resp.status_code, StatusCode::NO_CONTENT),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_delete_missing_category_returns_404() {
+        let config = in_memory_config();
+
+        match delete_category_handler(event(Some("Science"), None), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+    }
+}
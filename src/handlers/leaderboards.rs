@@ -0,0 +1,215 @@
+use apigateway::{APIErrorType::*, *};
+use chrono::Utc;
+use handlers::questions::{DEFAULT_PAGE, DEFAULT_SIZE};
+use models::Period;
+use responses::PaginatedResponse;
+
+/// Ranks the best score of each player in the category over the requested period.
+pub fn leaderboard_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let category: String = match event.get_path_param("category") {
+        Some(category) => category,
+        None => {
+            return Err(QueryParameterError {
+                parameter: "category".into(),
+                detail: Some("category required in path".into()),
+            }
+            .into())
+        }
+    };
+
+    let period = match event.get_query::<String>("period") {
+        Some(period) => period.parse::<Period>().map_err(|detail| {
+            APIErrorResponse::from(QueryParameterError {
+                parameter: "period".into(),
+                detail: Some(detail),
+            })
+        })?,
+        None => Period::All,
+    };
+
+    let page = match event.get_query::<i64>("page") {
+        Some(x) if x >= DEFAULT_PAGE => x,
+        _ => DEFAULT_PAGE,
+    };
+    let size = match event.get_query::<i64>("size") {
+        Some(x) if x >= DEFAULT_SIZE => x,
+        _ => DEFAULT_SIZE,
+    };
+
+    let since = period.since(Utc::now());
+    let total = config.leaderboards.count_players(&category, since)?;
+    let entries = match total {
+        0 => vec![],
+        _ => config
+            .leaderboards
+            .get_leaderboard(&category, since, page, size)?,
+    };
+
+    let paginated_response =
+        PaginatedResponse::new(entries, page as u32, total as u32, size as u32);
+
+    Ok(APIGatewayResponse::new(200, Some(&paginated_response)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::in_memory_config;
+    use handlers::sessions::{
+        answer_session_handler, start_session_handler, SessionProgress, SESSION_TOKEN_HEADER,
+    };
+    use http::StatusCode;
+    use models::{Category, Choice, LeaderboardEntry, Question};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn config_with_questions() -> Config {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Joke"), Some(true));
+        for i in 0..3 {
+            let _ = config.questions.save_question(&Question {
+                id: None,
+                question: format!("Joke {}", i),
+                category: "Joke".into(),
+                choices: vec![
+                    Choice {
+                        id: None,
+                        title: "Punchline".into(),
+                        correct: true,
+                    },
+                    Choice {
+                        id: None,
+                        title: "Silence".into(),
+                        correct: false,
+                    },
+                ],
+                difficulty: Default::default(),
+                tags: vec![],
+                updated_at: None,
+            });
+        }
+        config
+    }
+
+    /**
+     * Plays a full session through the handlers, answering `correct` questions correctly.
+     *
+     * The player is the principal of the authorizer; the name in the body is ignored.
+     */
+    fn play(config: &Config, player: Option<&str>, correct: usize) {
+        let authorizer = player.map(|player| {
+            let mut authorizer = HashMap::new();
+            authorizer.insert("principalId".to_string(), json!(player));
+            authorizer
+        });
+        let progress: SessionProgress = start_session_handler(
+            APIGatewayEvent {
+                body: Some("{\"category\": \"Joke\", \"player\": \"mallory\"}".into()),
+                request_context: Some(RequestContext {
+                    authorizer,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            config.clone(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        let mut path_parameters = HashMap::new();
+        path_parameters.insert("id".to_string(), progress.session.id.unwrap().to_string());
+        let mut headers = HashMap::new();
+        headers.insert(SESSION_TOKEN_HEADER.to_string(), progress.token.unwrap());
+
+        for (index, question) in progress.questions.unwrap().iter().enumerate() {
+            let choice = match index < correct {
+                true => &question.choices[0],
+                false => &question.choices[1],
+            };
+            let event = APIGatewayEvent {
+                path_parameters: Some(path_parameters.clone()),
+                headers: Some(headers.clone()),
+                body: Some(format!(
+                    "{{\"question_id\": {}, \"choice_id\": {}}}",
+                    question.id.unwrap(),
+                    choice.id.unwrap()
+                )),
+                ..Default::default()
+            };
+            answer_session_handler(event, config.clone()).unwrap();
+        }
+    }
+
+    fn event(period: Option<&str>) -> APIGatewayEvent {
+        let mut path_parameters = HashMap::new();
+        path_parameters.insert("category".to_string(), "Joke".to_string());
+
+        APIGatewayEvent {
+            path: "/leaderboards/Joke".into(),
+            path_parameters: Some(path_parameters),
+            query: period.map(|period| {
+                let mut query = HashMap::new();
+                query.insert("period".to_string(), period.to_string());
+                query
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_completed_sessions_are_ranked() {
+        let config = config_with_questions();
+        play(&config, Some("ada"), 2);
+        play(&config, Some("alan"), 3);
+        play(&config, Some("grace"), 2);
+        play(&config, None, 3);
+
+        match leaderboard_handler(event(Some("daily")), config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::OK);
+
+                let leaderboard: PaginatedResponse<LeaderboardEntry> = resp.parse().unwrap();
+                let ranking: Vec<(i64, String, i64)> = leaderboard
+                    .data
+                    .into_iter()
+                    .map(|entry| (entry.rank, entry.player, entry.score))
+                    .collect();
+                assert_eq!(
+                    ranking,
+                    vec![
+                        (1, "alan".to_string(), 3),
+                        (2, "ada".to_string(), 2),
+                        (3, "grace".to_string(), 2),
+                    ]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_leaderboard() {
+        match leaderboard_handler(event(None), config_with_questions()) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                let leaderboard: PaginatedResponse<LeaderboardEntry> = resp.parse().unwrap();
+                assert!(leaderboard.data.is_empty());
+                assert!(leaderboard.last);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_period_returns_400() {
+        match leaderboard_handler(event(Some("monthly")), in_memory_config()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+pub mod categories;
+pub mod category;
+pub mod leaderboards;
+pub mod new_question;
+pub mod question;
+pub mod questions;
+pub mod quiz;
+pub mod sessions;
+pub mod update_category_active;
+
+use apigateway::{APIErrorResponse, APIErrorSource};
+use auth::Permission;
+use router::Router;
+
+/**
+ * `Cache-Control` of the public categories and questions.
+ *
+ * Clients may reuse a response for a minute, then revalidate it with its `ETag`.
+ */
+pub const PUBLIC_CACHE_CONTROL: &str = "public, max-age=60";
+
+#[cfg(test)]
+use apigateway::Config;
+/// The error of a question whose category does not exist. Categories are only created through
+/// `/categories`.
+pub fn unknown_category(category: &str) -> APIErrorResponse {
+    APIErrorResponse::error(
+        422,
+        "question.category.unknown".into(),
+        "Unknown Category".into(),
+        Some(format!("Category '{}' does not exist", category)),
+        Some(APIErrorSource::Pointer {
+            pointer: "/category".into(),
+        }),
+    )
+}
+
+#[cfg(test)]
+use in_memory::InMemoryStore;
+#[cfg(test)]
+use std::rc::Rc;
+
+/**
+ * Routes served by the `api` lambda.
+ *
+ * Write endpoints and the `/admin` endpoints declare the permission they require. It is checked
+ * by `lambda_adapter` if bearer tokens are configured (see `AuthConfig`), and otherwise left to
+ * the API Gateway authorizer.
+ */
+pub fn api_router() -> Router {
+    Router::new()
+        .route("GET", "/categories", categories::categories_handler)
+        .route("POST", "/categories", category::new_category_handler)
+        .requires(Permission::CategoriesAdmin)
+        .route(
+            "PATCH",
+            "/categories/{name}",
+            category::rename_category_handler,
+        )
+        .requires(Permission::CategoriesAdmin)
+        .route(
+            "DELETE",
+            "/categories/{name}",
+            category::delete_category_handler,
+        )
+        .requires(Permission::CategoriesAdmin)
+        .route(
+            "GET",
+            "/admin/categories",
+            categories::category_summaries_handler,
+        )
+        .requires(Permission::CategoriesAdmin)
+        .route(
+            "POST",
+            "/categories/{category}/active",
+            update_category_active::update_category_active_handler,
+        )
+        .requires(Permission::CategoriesAdmin)
+        .route("GET", "/questions", questions::questions_handler)
+        .route("GET", "/quiz", quiz::quiz_handler)
+        .route(
+            "GET",
+            "/leaderboards/{category}",
+            leaderboards::leaderboard_handler,
+        )
+        .route("POST", "/sessions", sessions::start_session_handler)
+        .route("GET", "/sessions/{id}", sessions::get_session_handler)
+        .route(
+            "POST",
+            "/sessions/{id}/answers",
+            sessions::answer_session_handler,
+        )
+        .route(
+            "POST",
+            "/questions/{id}/answer",
+            question::answer_question_handler,
+        )
+        .route(
+            "GET",
+            "/admin/questions",
+            questions::admin_questions_handler,
+        )
+        .requires(Permission::QuestionsWrite)
+        .route(
+            "GET",
+            "/admin/questions/{id}",
+            question::admin_get_question_handler,
+        )
+        .requires(Permission::QuestionsWrite)
+        .route("POST", "/questions", new_question::new_question_handler)
+        .requires(Permission::QuestionsWrite)
+        .route("GET", "/questions/{id}", question::get_question_handler)
+        .route("PUT", "/questions/{id}", question::update_question_handler)
+        .requires(Permission::QuestionsWrite)
+        .route(
+            "DELETE",
+            "/questions/{id}",
+            question::delete_question_handler,
+        )
+        .requires(Permission::QuestionsWrite)
+}
+
+#[cfg(test)]
+pub fn in_memory_config() -> Config {
+    let store = Rc::new(InMemoryStore::new());
+    Config::new(store.clone(), store.clone(), store.clone(), store)
+}
+
+/**
+ * Asserts that the error has a pointer into the body and that every pointer resolves in the
+ * submitted JSON, so that clients can highlight the offending values.
+ */
+#[cfg(test)]
+pub fn assert_pointers_resolve(body: &str, error: &APIErrorResponse) {
+    let document: serde_json::Value = serde_json::from_str(body).expect("body must be valid JSON");
+    assert!(!error.errors().is_empty());
+    for error in error.errors() {
+        match error.source {
+            Some(APIErrorSource::Pointer { ref pointer }) => assert!(
+                document.pointer(pointer).is_some(),
+                "'{}' does not resolve in {}",
+                pointer,
+                body
+            ),
+            ref source => panic!("Expected a pointer, got {:?}", source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apigateway::APIGatewayEvent;
+    use models::{Category, Question};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_api_router_routes_update_category_active() {
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Science"));
+
+        let event = APIGatewayEvent {
+            http_method: "POST".into(),
+            path: "/categories/Science/active".into(),
+            body: Some("{\"active\": true }".into()),
+            ..Default::default()
+        };
+
+        match api_router().handle(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+                let status: HashMap<String, bool> = resp.parse().unwrap();
+                assert_eq!(status.get("active"), Some(&true));
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_bodies_point_into_the_body() {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Joke"), Some(true));
+        let question: Question = serde_json::from_str(
+            r#"{"question": "Why?", "category": "Joke", "choices": [
+                {"title": "Because", "correct": true}, {"title": "Why not", "correct": false}
+            ]}"#,
+        )
+        .unwrap();
+        let id = config
+            .questions
+            .save_question(&question)
+            .unwrap()
+            .id
+            .unwrap();
+
+        let request = |method: &str, path: &str, headers: &HashMap<String, String>, body: &str| {
+            let event = APIGatewayEvent {
+                http_method: method.into(),
+                path: path.into(),
+                headers: Some(headers.clone()),
+                body: Some(body.into()),
+                ..Default::default()
+            };
+            api_router().handle(event, config.clone())
+        };
+
+        let started = request(
+            "POST",
+            "/sessions",
+            &HashMap::new(),
+            r#"{"category": "Joke", "count": 1}"#,
+        )
+        .unwrap();
+        let started: serde_json::Value = started.parse().unwrap();
+        let mut session_headers = HashMap::new();
+        session_headers.insert(
+            sessions::SESSION_TOKEN_HEADER.to_string(),
+            started["token"].as_str().unwrap().to_string(),
+        );
+
+        let routes = [
+            (
+                "POST".to_string(),
+                format!("/questions/{}/answer", id),
+                HashMap::new(),
+                r#"{"choice_id": "first"}"#,
+            ),
+            (
+                "POST".to_string(),
+                "/sessions".to_string(),
+                HashMap::new(),
+                r#"{"category": "Joke", "count": "all"}"#,
+            ),
+            (
+                "POST".to_string(),
+                format!("/sessions/{}/answers", started["id"]),
+                session_headers,
+                r#"{"question_id": 1, "choice_id": [1]}"#,
+            ),
+            (
+                "POST".to_string(),
+                "/categories/Joke/active".to_string(),
+                HashMap::new(),
+                r#"{"active": "yes"}"#,
+            ),
+        ];
+        for (method, path, headers, wrong_type) in routes.iter() {
+            for body in &["{}", "[]", wrong_type] {
+                match request(method, path, headers, body) {
+                    Ok(resp) => panic!("Expected error for {} {}, got {}", method, path, resp),
+                    Err(err) => {
+                        assert_eq!(err.status_code(), 400, "{} {} {}", method, path, body);
+                        assert_pointers_resolve(body, &err);
+                    }
+                }
+            }
+        }
+    }
+}
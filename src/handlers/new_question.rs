@@ -0,0 +1,333 @@
+use apigateway::{APIErrorType::*, *};
+use handlers::unknown_category;
+use models::Question;
+use repositories::RepositoryError;
+
+/// Questions can only be added to existing categories; unknown categories are not created.
+pub fn new_question_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let question: Question =
+        match event.parse_with_validator(&|question| config.question_rules.validate(question)) {
+            Ok(Some(question)) => question.trimmed(),
+            Ok(None) => {
+                return Err(BodyParameterError {
+                    pointer: "".into(),
+                    detail: Some("'Question' required in body".into()),
+                }
+                .into())
+            }
+            Err(e) => return Err(e),
+        };
+
+    let new_question = match config.questions.save_question(&question) {
+        Ok(new_question) => new_question,
+        Err(RepositoryError::DatabaseError(ref code, _)) if code == "23503" => {
+            return Err(unknown_category(&question.category))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let api_response = APIGatewayResponse::new(201, Some(&new_question)).unwrap();
+    Ok(api_response)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use handlers::{assert_pointers_resolve, in_memory_config};
+    use http::StatusCode;
+    use models::Category;
+    use validation::QuestionRules;
+
+    #[test]
+    fn test_empty_body_returns_400() {
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: None,
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match new_question_handler(event, config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_invalid_json_returns_400() {
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: None,
+            body: Some("{}".into()),
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match new_question_handler(event, config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                println!("TEST. Invalid json. Error: '{:?}'", err);
+                assert_eq!(err.status_code(), StatusCode::BAD_REQUEST)
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_question() {
+        let question_json = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices":[{
+                "title":"To get to the other side",
+                "correct":true
+            },{
+                "title":"To commit suicide",
+                "correct":false
+            }]
+        }"#;
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: None,
+            body: Some(question_json.into()),
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Joke"));
+
+        match new_question_handler(event, config) {
+            Ok(apiresponse) => {
+                let question: Question = apiresponse.parse().unwrap();
+                let choice = question.choices.first().unwrap();
+
+                assert_eq!(apiresponse.status_code, StatusCode::CREATED);
+                assert!(question.id.is_some());
+                assert_eq!(
+                    question.question,
+                    "Why did the chicken cross the road".to_string()
+                );
+                assert_eq!(question.category, "Joke".to_string());
+                assert_eq!(question.choices.len(), 2);
+                assert!(choice.id.is_some());
+                assert_eq!(choice.title, "To get to the other side".to_string());
+                assert!(choice.correct);
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_unknown_category_returns_422() {
+        let question_json = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices":[
+                {"title":"To get to the other side", "correct":true},
+                {"title":"To commit suicide", "correct":false}
+            ]
+        }"#;
+        let event = APIGatewayEvent {
+            body: Some(question_json.into()),
+            ..Default::default()
+        };
+        let config = in_memory_config();
+
+        match new_question_handler(event, config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                assert_eq!(err.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+                assert_pointers_resolve(question_json, &err);
+            }
+        }
+        assert!(config
+            .categories
+            .list_category_summaries()
+            .unwrap()
+            .categories
+            .is_empty());
+    }
+
+    #[test]
+    fn test_text_is_saved_trimmed() {
+        let question_json = r#"{
+            "question": " Why did the chicken cross the road ",
+            "category": " Joke ",
+            "choices":[
+                {"title":" To get to the other side", "correct":true},
+                {"title":"To commit suicide ", "correct":false}
+            ]
+        }"#;
+        let event = APIGatewayEvent {
+            body: Some(question_json.into()),
+            ..Default::default()
+        };
+        let config = in_memory_config();
+        let _ = config.categories.save_category(&Category::new("Joke"));
+
+        match new_question_handler(event, config.clone()) {
+            Ok(apiresponse) => {
+                let question: Question = apiresponse.parse().unwrap();
+                assert_eq!(question.question, "Why did the chicken cross the road");
+                assert_eq!(question.category, "Joke");
+                assert_eq!(question.choices[0].title, "To get to the other side");
+                assert_eq!(question.choices[1].title, "To commit suicide");
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+        assert_eq!(
+            config
+                .categories
+                .list_category_summaries()
+                .unwrap()
+                .categories
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_errors_point_into_the_body() {
+        let question_json = r#"{
+            "question": " ",
+            "category": "Joke",
+            "choices":[
+                {"title":"To get to the other side", "correct":true},
+                {"title":"", "correct":false}
+            ]
+        }"#;
+        let wrong_type = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices":[{"title":"To get to the other side", "correct":"yes"}]
+        }"#;
+        let unknown_difficulty = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "difficulty": "expert",
+            "choices":[{"title":"To get to the other side", "correct":true}]
+        }"#;
+
+        for (json, pointers) in &[
+            (question_json, vec!["/question", "/choices/1/title"]),
+            (wrong_type, vec!["/choices/0/correct"]),
+            (unknown_difficulty, vec!["/difficulty"]),
+        ] {
+            let event = APIGatewayEvent {
+                body: Some(json.to_string()),
+                ..Default::default()
+            };
+            match new_question_handler(event, in_memory_config()) {
+                Ok(resp) => panic!("Expected error, got {}", resp),
+                Err(err) => {
+                    assert_pointers_resolve(json, &err);
+                    let actual: Vec<String> = err
+                        .errors()
+                        .iter()
+                        .map(|error| match error.source {
+                            Some(APIErrorSource::Pointer { ref pointer }) => pointer.clone(),
+                            _ => panic!("Expected a pointer"),
+                        })
+                        .collect();
+                    assert_eq!(&actual, pointers);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_question_rules_come_from_config() {
+        let question_json = r#"{
+            "question": "Which of these are prime numbers?",
+            "category": "Maths",
+            "choices":[
+                {"title":"2", "correct":true},
+                {"title":"3", "correct":true},
+                {"title":"4", "correct":false},
+                {"title":" 4", "correct":false}
+            ]
+        }"#;
+
+        let event = || APIGatewayEvent {
+            body: Some(question_json.into()),
+            ..Default::default()
+        };
+
+        match new_question_handler(event(), in_memory_config()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                let codes: Vec<&str> = err.errors().iter().map(|e| e.code.as_str()).collect();
+                assert_eq!(
+                    codes,
+                    vec![
+                        "question.choices.multiple_correct",
+                        "question.choices.duplicate"
+                    ]
+                );
+            }
+        }
+
+        let config = in_memory_config().with_question_rules(QuestionRules {
+            single_answer: false,
+            max_choices: 3,
+            ..Default::default()
+        });
+        match new_question_handler(event(), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                let codes: Vec<&str> = err.errors().iter().map(|e| e.code.as_str()).collect();
+                assert_eq!(
+                    codes,
+                    vec!["question.choices.too_many", "question.choices.duplicate"]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_question_with_multiple_correct_options_returns_400() {
+        let question_json = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices":[{
+                "title":"To get to the other side",
+                "correct":true
+            },{
+                "title":"To commit suicide",
+                "correct":true
+            }]
+        }"#;
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: None,
+            body: Some(question_json.into()),
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match new_question_handler(event, config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                println!(
+                    "TEST. Invalid json. Error: '{:?}'",
+                    serde_json::to_string(&err)
+                );
+                assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+                assert_eq!(err.errors()[0].code, "question.choices.multiple_correct");
+                assert_pointers_resolve(question_json, &err);
+            }
+        }
+    }
+}
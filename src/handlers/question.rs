@@ -0,0 +1,437 @@
+use apigateway::{APIErrorType::*, *};
+use handlers::{unknown_category, PUBLIC_CACHE_CONTROL};
+use models::{Answer, PlayerQuestion, Question};
+use repositories::RepositoryError;
+
+fn question_id(event: &APIGatewayEvent) -> Result<i64, APIErrorResponse> {
+    match event.get_path_param("id") {
+        Some(id) => Ok(id),
+        None => Err(QueryParameterError {
+            parameter: "id".into(),
+            detail: Some("numeric question id required in path".into()),
+        }
+        .into()),
+    }
+}
+
+fn question_not_found(id: i64) -> APIErrorResponse {
+    NotFound {
+        detail: Some(format!("Question '{}' does not exist", id)),
+    }
+    .into()
+}
+
+/**
+ * Returns the player projection of the question, without the correct choice.
+ *
+ * Questions of inactive categories are not found.
+ */
+pub fn get_question_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let id = question_id(&event)?;
+
+    match config.questions.get_active_question(id)? {
+        Some(question) => {
+            let last_modified = question.updated_at;
+            let question = PlayerQuestion::from(question);
+            Ok(APIGatewayResponse::new(200, Some(&question))
+                .unwrap()
+                .with_cache_control(PUBLIC_CACHE_CONTROL)
+                .with_last_modified(last_modified)
+                .conditional(&event))
+        }
+        None => Err(question_not_found(id)),
+    }
+}
+
+/// Returns the full question, including the correct choice, for administrators.
+pub fn admin_get_question_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let id = question_id(&event)?;
+
+    match config.questions.get_question(id)? {
+        Some(question) => Ok(APIGatewayResponse::new(200, Some(&question))
+            .unwrap()
+            .with_last_modified(question.updated_at)
+            .conditional(&event)),
+        None => Err(question_not_found(id)),
+    }
+}
+
+/// Checks the submitted choice, revealing the correct choice only after answering.
+pub fn answer_question_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let id = question_id(&event)?;
+
+    let answer = match event.parse::<Answer>() {
+        Ok(Some(answer)) => answer,
+        Ok(None) => {
+            return Err(BodyParameterError {
+                pointer: "".into(),
+                detail: Some("Expected {\"choice_id\": <choice id> }".into()),
+            }
+            .into())
+        }
+        Err(e) => return Err(e),
+    };
+
+    let question = match config.questions.get_active_question(id)? {
+        Some(question) => question,
+        None => return Err(question_not_found(id)),
+    };
+
+    match question.check_answer(answer.choice_id) {
+        Some(result) => Ok(APIGatewayResponse::new(200, Some(&result)).unwrap()),
+        None => Err(BodyParameterError {
+            pointer: "/choice_id".into(),
+            detail: Some(format!(
+                "Choice '{}' does not belong to question '{}'",
+                answer.choice_id, id
+            )),
+        }
+        .into()),
+    }
+}
+
+/// Questions can only be moved to existing categories; unknown categories are not created.
+pub fn update_question_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let id = question_id(&event)?;
+
+    let question: Question =
+        match event.parse_with_validator(&|question| config.question_rules.validate(question)) {
+            Ok(Some(question)) => question.trimmed(),
+            Ok(None) => {
+                return Err(BodyParameterError {
+                    pointer: "".into(),
+                    detail: Some("'Question' required in body".into()),
+                }
+                .into())
+            }
+            Err(e) => return Err(e),
+        };
+
+    match config.questions.update_question(id, &question) {
+        Ok(Some(question)) => Ok(APIGatewayResponse::new(200, Some(&question)).unwrap()),
+        Ok(None) => Err(question_not_found(id)),
+        Err(RepositoryError::DatabaseError(ref code, _)) if code == "23503" => {
+            Err(unknown_category(&question.category))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Questions that were played in quiz sessions are kept, so that their scores never change.
+pub fn delete_question_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let id = question_id(&event)?;
+
+    match config.questions.delete_question(id) {
+        Ok(true) => Ok(APIGatewayResponse::new::<Question>(204, None).unwrap()),
+        Ok(false) => Err(question_not_found(id)),
+        Err(RepositoryError::DatabaseError(ref code, _)) if code == "23503" => Err(Conflict {
+            detail: Some(format!("Question '{}' was played in quiz sessions", id)),
+        }
+        .into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use handlers::{assert_pointers_resolve, in_memory_config};
+    use http::StatusCode;
+    use models::{AnswerResult, Category, Choice, Session};
+    use std::collections::HashMap;
+
+    fn event(id: &str, body: Option<&str>) -> APIGatewayEvent {
+        let mut path_parameters = HashMap::new();
+        path_parameters.insert("id".to_string(), id.to_string());
+
+        APIGatewayEvent {
+            path: format!("/questions/{}", id),
+            path_parameters: Some(path_parameters),
+            body: body.map(|body| body.into()),
+            ..Default::default()
+        }
+    }
+
+    fn save_question(config: &Config) -> Question {
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Joke"), Some(true));
+        config
+            .questions
+            .save_question(&Question {
+                id: None,
+                question: "Why did the chicken cross the road".into(),
+                category: "Joke".into(),
+                choices: vec![
+                    Choice {
+                        id: None,
+                        title: "To get to the other side".into(),
+                        correct: true,
+                    },
+                    Choice {
+                        id: None,
+                        title: "To commit suicide".into(),
+                        correct: false,
+                    },
+                ],
+                difficulty: Default::default(),
+                tags: vec![],
+                updated_at: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_get_question() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+
+        match get_question_handler(event(&format!("{}", saved.id.unwrap()), None), config) {
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::OK);
+                let question: PlayerQuestion = resp.parse().unwrap();
+                assert_eq!(question.id, saved.id);
+                assert_eq!(question.choices.len(), 2);
+                assert!(!resp.body.contains("correct"));
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_get_question_honours_if_none_match() {
+        let config = in_memory_config();
+        let id = format!("{}", save_question(&config).id.unwrap());
+
+        let resp = get_question_handler(event(&id, None), config.clone()).unwrap();
+        assert!(resp.headers.contains_key("Last-Modified"));
+
+        let mut cached = event(&id, None);
+        let mut headers = HashMap::new();
+        headers.insert(
+            "If-None-Match".to_string(),
+            resp.headers.get("ETag").unwrap().clone(),
+        );
+        cached.headers = Some(headers);
+
+        let resp = get_question_handler(cached, config).unwrap();
+        assert_eq!(resp.status_code, StatusCode::NOT_MODIFIED);
+        assert!(resp.body.is_empty());
+    }
+
+    #[test]
+    fn test_admin_get_question_includes_correct_choice() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+
+        match admin_get_question_handler(event(&format!("{}", saved.id.unwrap()), None), config) {
+            Ok(resp) => {
+                let question: Question = resp.parse().unwrap();
+                assert!(question.choices[0].correct);
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_answer_question() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+        let shared = config.clone();
+        let id = format!("{}", saved.id.unwrap());
+        let correct_id = saved.choices[0].id.unwrap();
+        let wrong_id = saved.choices[1].id.unwrap();
+
+        let body = format!("{{\"choice_id\": {}}}", wrong_id);
+        match answer_question_handler(event(&id, Some(&body)), config) {
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::OK);
+                let result: AnswerResult = resp.parse().unwrap();
+                assert_eq!(
+                    result,
+                    AnswerResult {
+                        question_id: saved.id.unwrap(),
+                        choice_id: wrong_id,
+                        correct: false,
+                        correct_choice_id: Some(correct_id),
+                    }
+                );
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+
+        match answer_question_handler(event(&id, Some("{\"choice_id\": 999}")), shared) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_questions_of_inactive_categories_are_not_public() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+        let id = format!("{}", saved.id.unwrap());
+        config
+            .categories
+            .set_category_active("Joke", false)
+            .unwrap();
+
+        match get_question_handler(event(&id, None), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+
+        let body = format!("{{\"choice_id\": {}}}", saved.choices[0].id.unwrap());
+        match answer_question_handler(event(&id, Some(&body)), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+
+        assert!(admin_get_question_handler(event(&id, None), config).is_ok());
+    }
+
+    #[test]
+    fn test_missing_question_returns_404() {
+        let config = in_memory_config();
+
+        match get_question_handler(event("42", None), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+    }
+
+    #[test]
+    fn test_non_numeric_id_returns_400() {
+        let config = in_memory_config();
+
+        match delete_question_handler(event("abc", None), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_update_question() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+        let question_json = r#"{
+            "question": "Why did the duck cross the road",
+            "category": "Bird Joke",
+            "choices":[{
+                "title":"Because the chicken retired",
+                "correct":true
+            },{
+                "title":"To get to the other side",
+                "correct":false
+            }]
+        }"#;
+
+        let id = format!("{}", saved.id.unwrap());
+        match update_question_handler(event(&id, Some(question_json)), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                assert_eq!(err.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+                assert_pointers_resolve(question_json, &err);
+            }
+        }
+        assert_eq!(
+            config
+                .categories
+                .list_category_summaries()
+                .unwrap()
+                .categories
+                .len(),
+            1
+        );
+
+        let _ = config.categories.save_category(&Category::new("Bird Joke"));
+        match update_question_handler(event(&id, Some(question_json)), config) {
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::OK);
+                let question: Question = resp.parse().unwrap();
+                assert_eq!(question.id, saved.id);
+                assert_eq!(question.category, "Bird Joke");
+                assert_eq!(question.choices.len(), 2);
+                assert!(question.choices.iter().all(|choice| choice.id.is_some()));
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_update_with_multiple_correct_options_returns_400() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+        let question_json = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices":[{
+                "title":"To get to the other side",
+                "correct":true
+            },{
+                "title":"To commit suicide",
+                "correct":true
+            }]
+        }"#;
+
+        let id = format!("{}", saved.id.unwrap());
+        match update_question_handler(event(&id, Some(question_json)), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+                assert_pointers_resolve(question_json, &err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_delete_question() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+        let id = format!("{}", saved.id.unwrap());
+
+        match delete_question_handler(event(&id, None), config.clone()) {
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::NO_CONTENT);
+                assert!(resp.body.is_empty());
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+
+        match delete_question_handler(event(&id, None), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+    }
+
+    #[test]
+    fn test_delete_question_of_session_returns_409() {
+        let config = in_memory_config();
+        let saved = save_question(&config);
+        config
+            .sessions
+            .create_session(&Session::new("Joke", &[saved.id.unwrap()], Utc::now()))
+            .unwrap();
+
+        match delete_question_handler(event(&format!("{}", saved.id.unwrap()), None), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::CONFLICT),
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use apigateway::{APIErrorType::*, *};
+use handlers::PUBLIC_CACHE_CONTROL;
+use models::{normalize_tags, DifficultyRange, PlayerQuestion, Question, QuestionFilter, TagMatch};
+use responses::PaginatedResponse;
+use serde::Serialize;
+
+pub const DEFAULT_PAGE: i64 = 1;
+pub const DEFAULT_SIZE: i64 = 10;
+
+/**
+ * Lists the questions of a category without revealing the correct choices.
+ *
+ * The `difficulty` query parameter selects a single difficulty (`easy`) or a range
+ * (`easy..medium`); the total is counted with the same filter. Repeated `tag` parameters select
+ * the questions having any of the tags, or all of them with `match=all`, across categories
+ * unless a `category` is given as well.
+ */
+pub fn questions_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    Ok(questions_page(&event, &config, PlayerQuestion::from)?
+        .with_cache_control(PUBLIC_CACHE_CONTROL)
+        .conditional(&event))
+}
+
+/// Lists the questions of a category including the correct choices, for administrators.
+pub fn admin_questions_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    Ok(questions_page(&event, &config, |question| question)?.conditional(&event))
+}
+
+fn questions_page<T: Serialize>(
+    event: &APIGatewayEvent,
+    config: &Config,
+    projection: fn(Question) -> T,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let page = match event.get_query::<i64>("page") {
+        Some(x) if x >= DEFAULT_PAGE => x,
+        _ => DEFAULT_PAGE,
+    };
+    let size = match event.get_query::<i64>("size") {
+        Some(x) if x >= DEFAULT_SIZE => x,
+        _ => DEFAULT_SIZE,
+    };
+    let category = event.get_query::<String>("category");
+    let tags = normalize_tags(&event.get_query_values("tag"));
+    if category.is_none() && tags.is_empty() {
+        return Err(APIErrorResponse::from(QueryParameterError {
+            parameter: "category".into(),
+            detail: Some("Missing 'category' or 'tag' parameter".into()),
+        }));
+    }
+
+    let tag_match = match event.get_query::<String>("match") {
+        Some(tag_match) => tag_match.parse::<TagMatch>().map_err(|detail| {
+            APIErrorResponse::from(QueryParameterError {
+                parameter: "match".into(),
+                detail: Some(detail),
+            })
+        })?,
+        None => TagMatch::default(),
+    };
+
+    let difficulty = match event.get_query::<String>("difficulty") {
+        Some(difficulty) => difficulty.parse::<DifficultyRange>().map_err(|detail| {
+            APIErrorResponse::from(QueryParameterError {
+                parameter: "difficulty".into(),
+                detail: Some(detail),
+            })
+        })?,
+        None => DifficultyRange::default(),
+    };
+    let filter = QuestionFilter {
+        category,
+        difficulty,
+        tags,
+        tag_match,
+    };
+
+    let total = config.questions.count_questions(&filter)?;
+    let questions = match total {
+        0 => vec![],
+        _ => config.questions.get_questions(&filter, page, size)?,
+    };
+
+    let last_modified = questions
+        .iter()
+        .filter_map(|question| question.updated_at)
+        .max();
+    let paginated_response = PaginatedResponse::new(
+        questions.into_iter().map(projection).collect(),
+        page as u32,
+        total as u32,
+        size as u32,
+    );
+
+    let api_response = APIGatewayResponse::new(200, Some(&paginated_response))
+        .unwrap()
+        .with_last_modified(last_modified);
+    Ok(api_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::in_memory_config;
+    use http::StatusCode;
+    use models::{Category, Difficulty};
+    use std::collections::HashMap;
+
+    const CHOICES: &str =
+        r#"[{"title": "Yes", "correct": true}, {"title": "No", "correct": false}]"#;
+
+    #[test]
+    fn test_empty_query_returns_400() {
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: None,
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match questions_handler(event, config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => {
+                assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_page_and_size_uses_defaults() {
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: Some(query),
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match questions_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::OK);
+
+                let paginated_response: PaginatedResponse<PlayerQuestion> = resp.parse().unwrap();
+                assert_eq!(paginated_response.page, DEFAULT_PAGE as u32);
+                assert!(paginated_response.size <= DEFAULT_SIZE as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_values_uses_defaults() {
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+        query.insert("page".into(), "0".into());
+        query.insert("size".into(), "0".into());
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: Some(query),
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match questions_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let paginated_response: PaginatedResponse<PlayerQuestion> = resp.parse().unwrap();
+                assert_eq!(paginated_response.page, DEFAULT_PAGE as u32);
+                assert!(paginated_response.size <= DEFAULT_SIZE as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_negative_values_uses_defaults() {
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+        query.insert("page".into(), "0".into());
+        query.insert("size".into(), "0".into());
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: Some(query),
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match questions_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let paginated_response: PaginatedResponse<PlayerQuestion> = resp.parse().unwrap();
+                assert_eq!(paginated_response.page, DEFAULT_PAGE as u32);
+                assert!(paginated_response.size <= DEFAULT_SIZE as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_alphabetic_data_uses_defaults() {
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+        query.insert("page".into(), "PAGE".into());
+        query.insert("size".into(), "SIZE".into());
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: Some(query),
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        let config = in_memory_config();
+
+        match questions_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let paginated_response: PaginatedResponse<PlayerQuestion> = resp.parse().unwrap();
+                assert_eq!(paginated_response.page, DEFAULT_PAGE as u32);
+                assert!(paginated_response.size <= DEFAULT_SIZE as u32);
+            }
+        }
+    }
+
+    fn populate_db(config: &Config) {
+        let question_json = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices":[{
+                "title":"To get to the other side",
+                "correct":true
+            },{
+                "title":"To commit suicide",
+                "correct":false
+            }]
+        }"#;
+
+        let question: Question = serde_json::from_str(question_json).unwrap();
+
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new(question.category.clone()), Some(true));
+
+        let _ = config.questions.save_question(&question).unwrap();
+    }
+
+    #[test]
+    fn test_load_questions_retuns_paginated_list() {
+        let config = in_memory_config();
+        populate_db(&config);
+
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+
+        let event = APIGatewayEvent {
+            path: "/".into(),
+            query: Some(query),
+            body: None,
+            path_parameters: None,
+            ..Default::default()
+        };
+
+        match questions_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let paginated_response: PaginatedResponse<PlayerQuestion> = resp.parse().unwrap();
+                assert_eq!(paginated_response.page, DEFAULT_PAGE as u32);
+                assert!(paginated_response.size <= DEFAULT_SIZE as u32);
+
+                let questions = paginated_response.data;
+                let first_question = questions.first().unwrap();
+                let first_choice = first_question.choices.first().unwrap();
+
+                assert!(!questions.is_empty());
+
+                assert_eq!(
+                    first_question.question,
+                    "Why did the chicken cross the road".to_string()
+                );
+                assert_eq!(first_question.category, "Joke".to_string());
+                assert_eq!(first_question.choices.len(), 2);
+                assert_eq!(first_choice.title, "To get to the other side".to_string());
+                assert!(!resp.body.contains("correct"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_questions_page_sets_cache_headers() {
+        let config = in_memory_config();
+        populate_db(&config);
+
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+
+        let event = || APIGatewayEvent {
+            query: Some(query.clone()),
+            ..Default::default()
+        };
+
+        let resp = questions_handler(event(), config.clone()).unwrap();
+        assert_eq!(
+            resp.headers.get("Cache-Control"),
+            Some(&PUBLIC_CACHE_CONTROL.to_string())
+        );
+        assert!(resp.headers.contains_key("Last-Modified"));
+
+        let mut headers = HashMap::new();
+        headers.insert(
+            "If-None-Match".to_string(),
+            resp.headers.get("ETag").unwrap().clone(),
+        );
+        let cached = APIGatewayEvent {
+            headers: Some(headers),
+            ..event()
+        };
+
+        let resp = questions_handler(cached, config).unwrap();
+        assert_eq!(resp.status_code, StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn test_difficulty_filter() {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Joke"), Some(true));
+        for (text, difficulty) in &[
+            ("Knock knock", Difficulty::Easy),
+            ("Pun", Difficulty::Medium),
+            ("Satire", Difficulty::Hard),
+            ("Irony", Difficulty::Hard),
+        ] {
+            let mut question: Question = serde_json::from_str(&format!(
+                r#"{{"question": "{}", "category": "Joke", "choices": {}}}"#,
+                text, CHOICES
+            ))
+            .unwrap();
+            question.difficulty = *difficulty;
+            config.questions.save_question(&question).unwrap();
+        }
+
+        let page = |difficulty: &str| {
+            let mut query = HashMap::<String, String>::new();
+            query.insert("category".into(), "Joke".into());
+            query.insert("difficulty".into(), difficulty.into());
+            questions_handler(
+                APIGatewayEvent {
+                    query: Some(query),
+                    ..Default::default()
+                },
+                config.clone(),
+            )
+        };
+
+        let hard: PaginatedResponse<PlayerQuestion> = page("hard").unwrap().parse().unwrap();
+        assert_eq!(hard.data.len(), 2);
+        assert!(hard
+            .data
+            .iter()
+            .all(|question| question.difficulty == Difficulty::Hard));
+
+        let easy_to_medium: PaginatedResponse<PlayerQuestion> =
+            page("easy..medium").unwrap().parse().unwrap();
+        let texts: Vec<String> = easy_to_medium
+            .data
+            .into_iter()
+            .map(|question| question.question)
+            .collect();
+        assert_eq!(texts, vec!["Knock knock", "Pun"]);
+
+        match page("expert") {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let config = in_memory_config();
+        for category in &["Space", "History"] {
+            let _ = config
+                .categories
+                .save_category_and_set_active(&Category::new(*category), Some(true));
+        }
+        for (text, category, tags) in &[
+            ("Apollo 11", "Space", r#"["space", "Moon"]"#),
+            ("Sputnik", "History", r#"["space", "cold war"]"#),
+            ("Berlin Wall", "History", r#"["cold war"]"#),
+        ] {
+            let question: Question = serde_json::from_str(&format!(
+                r#"{{"question": "{}", "category": "{}", "choices": {}, "tags": {}}}"#,
+                text, category, CHOICES, tags
+            ))
+            .unwrap();
+            config.questions.save_question(&question).unwrap();
+        }
+
+        let page = |tags: &[&str], tag_match: Option<&str>| {
+            let mut multi_value_query = HashMap::<String, Vec<String>>::new();
+            multi_value_query.insert(
+                "tag".into(),
+                tags.iter().map(|tag| tag.to_string()).collect(),
+            );
+            if let Some(tag_match) = tag_match {
+                multi_value_query.insert("match".into(), vec![tag_match.into()]);
+            }
+            let query = multi_value_query
+                .iter()
+                .filter_map(|(name, values)| {
+                    values.last().map(|value| (name.clone(), value.clone()))
+                })
+                .collect();
+            questions_handler(
+                APIGatewayEvent {
+                    query: Some(query),
+                    multi_value_query: Some(multi_value_query),
+                    ..Default::default()
+                },
+                config.clone(),
+            )
+        };
+        let texts = |tags: &[&str], tag_match: Option<&str>| -> Vec<String> {
+            let page: PaginatedResponse<PlayerQuestion> =
+                page(tags, tag_match).unwrap().parse().unwrap();
+            page.data
+                .into_iter()
+                .map(|question| question.question)
+                .collect()
+        };
+
+        assert_eq!(texts(&["Space"], None), vec!["Apollo 11", "Sputnik"]);
+        assert_eq!(
+            texts(&["moon", "cold war"], Some("any")),
+            vec!["Apollo 11", "Sputnik", "Berlin Wall"]
+        );
+        assert_eq!(texts(&["space", "cold war"], Some("all")), vec!["Sputnik"]);
+
+        let tagged: PaginatedResponse<PlayerQuestion> =
+            page(&["moon"], None).unwrap().parse().unwrap();
+        assert_eq!(tagged.data[0].tags, vec!["moon", "space"]);
+
+        match page(&["space"], Some("most")) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+        match page(&[" "], None) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_tag_filtered_pages_do_not_overlap() {
+        let config = in_memory_config();
+        for category in &["Space", "History"] {
+            let _ = config
+                .categories
+                .save_category_and_set_active(&Category::new(*category), Some(true));
+        }
+        let mut tagged = vec![];
+        for i in 0..DEFAULT_SIZE * 2 {
+            let (category, tag) = match i % 4 {
+                0 => ("History", "cold war"),
+                1 | 2 => ("Space", "space"),
+                _ => ("History", "space"),
+            };
+            let question: Question = serde_json::from_str(&format!(
+                r#"{{"question": "Question {}", "category": "{}", "choices": {}, "tags": ["{}"]}}"#,
+                i, category, CHOICES, tag
+            ))
+            .unwrap();
+            let saved = config.questions.save_question(&question).unwrap();
+            if tag == "space" {
+                tagged.push(saved.id);
+            }
+        }
+
+        let page = |page: i64| -> PaginatedResponse<PlayerQuestion> {
+            let mut query = HashMap::<String, String>::new();
+            query.insert("tag".into(), "space".into());
+            query.insert("page".into(), page.to_string());
+            questions_handler(
+                APIGatewayEvent {
+                    query: Some(query),
+                    ..Default::default()
+                },
+                config.clone(),
+            )
+            .unwrap()
+            .parse()
+            .unwrap()
+        };
+
+        let (first, second) = (page(1), page(2));
+        assert_eq!(
+            (first.page_count, first.last, second.last),
+            (2, false, true)
+        );
+        let ids: Vec<Option<i64>> = first
+            .data
+            .into_iter()
+            .chain(second.data)
+            .map(|question| question.id)
+            .collect();
+        assert_eq!(ids, tagged);
+    }
+
+    #[test]
+    fn test_admin_questions_include_correct_choices() {
+        let config = in_memory_config();
+        populate_db(&config);
+
+        let mut query = HashMap::<String, String>::new();
+        query.insert("category".into(), "Joke".into());
+
+        let event = APIGatewayEvent {
+            path: "/admin/questions".into(),
+            query: Some(query),
+            ..Default::default()
+        };
+
+        match admin_questions_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                let paginated_response: PaginatedResponse<Question> = resp.parse().unwrap();
+                let first_question = paginated_response.data.first().unwrap();
+
+                assert!(first_question.choices.first().unwrap().correct);
+                assert!(!first_question.choices.last().unwrap().correct);
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use apigateway::{APIErrorType::*, *};
+use models::PlayerQuestion;
+use sampling::random_seed;
+use serde_derive::{Deserialize, Serialize};
+
+pub const DEFAULT_COUNT: usize = 10;
+pub const MAX_COUNT: usize = 50;
+
+/// `seed` is returned even if it was not requested, so that a quiz can be replayed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Quiz {
+    pub category: String,
+    pub seed: u64,
+    pub data: Vec<PlayerQuestion>,
+}
+
+fn has_query(event: &APIGatewayEvent, name: &str) -> bool {
+    event
+        .query
+        .as_ref()
+        .is_some_and(|query| query.contains_key(name))
+}
+
+pub fn quiz_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let category = event
+        .get_query::<String>("category")
+        .ok_or(APIErrorResponse::from(QueryParameterError {
+            parameter: "category".into(),
+            detail: Some("Missing 'category' parameter".into()),
+        }))?;
+
+    let count = match event.get_query::<usize>("count") {
+        Some(count) if (1..=MAX_COUNT).contains(&count) => count,
+        None if !has_query(&event, "count") => DEFAULT_COUNT,
+        _ => {
+            return Err(QueryParameterError {
+                parameter: "count".into(),
+                detail: Some(format!("'count' must be between 1 and {}", MAX_COUNT)),
+            }
+            .into())
+        }
+    };
+
+    let seed = match event.get_query::<u64>("seed") {
+        Some(seed) => seed,
+        None if !has_query(&event, "seed") => random_seed(),
+        None => {
+            return Err(QueryParameterError {
+                parameter: "seed".into(),
+                detail: Some("'seed' must be a non-negative integer".into()),
+            }
+            .into())
+        }
+    };
+
+    let questions = config.questions.sample_questions(&category, count, seed)?;
+
+    let quiz = Quiz {
+        category,
+        seed,
+        data: questions.into_iter().map(PlayerQuestion::from).collect(),
+    };
+    Ok(APIGatewayResponse::new(200, Some(&quiz)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::in_memory_config;
+    use http::StatusCode;
+    use models::{Category, Choice, Question};
+    use std::collections::HashMap;
+
+    fn event(query: &[(&str, &str)]) -> APIGatewayEvent {
+        APIGatewayEvent {
+            path: "/quiz".into(),
+            query: Some(
+                query
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect::<HashMap<String, String>>(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn config_with_questions(count: usize) -> Config {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Joke"), Some(true));
+        for i in 0..count {
+            let _ = config.questions.save_question(&Question {
+                id: None,
+                question: format!("Joke {}", i),
+                category: "Joke".into(),
+                choices: vec![Choice {
+                    id: None,
+                    title: "Punchline".into(),
+                    correct: true,
+                }],
+                difficulty: Default::default(),
+                tags: vec![],
+                updated_at: None,
+            });
+        }
+        config
+    }
+
+    #[test]
+    fn test_quiz_samples_count_questions() {
+        let config = config_with_questions(30);
+
+        match quiz_handler(event(&[("category", "Joke"), ("count", "5")]), config) {
+            Ok(resp) => {
+                assert_eq!(resp.status_code, StatusCode::OK);
+                let quiz: Quiz = resp.parse().unwrap();
+                assert_eq!(quiz.data.len(), 5);
+                assert_eq!(quiz.data[0].choices.len(), 1);
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_quiz_with_seed_is_reproducible() {
+        let config = config_with_questions(30);
+        let shared = config.clone();
+        let query = [("category", "Joke"), ("seed", "1234")];
+
+        let first: Quiz = quiz_handler(event(&query), config)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let second: Quiz = quiz_handler(event(&query), shared)
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        assert_eq!(first.seed, 1234);
+        assert_eq!(first.data.len(), DEFAULT_COUNT);
+        let ids = |quiz: &Quiz| -> Vec<Option<i64>> { quiz.data.iter().map(|q| q.id).collect() };
+        assert_eq!(ids(&first), ids(&second));
+    }
+
+    #[test]
+    fn test_invalid_count_returns_400() {
+        for count in &["0", "51", "ten"] {
+            match quiz_handler(
+                event(&[("category", "Joke"), ("count", count)]),
+                config_with_questions(1),
+            ) {
+                Ok(resp) => panic!("Expected error, got {}", resp),
+                Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_category_returns_400() {
+        match quiz_handler(event(&[]), in_memory_config()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+}
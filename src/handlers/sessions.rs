@@ -0,0 +1,530 @@
+use apigateway::{APIErrorType::*, *};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use handlers::quiz::{DEFAULT_COUNT, MAX_COUNT};
+use models::{AnswerResult, PlayerQuestion, Score, Session, SessionAnswer};
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use sampling::random_seed;
+use serde_derive::{Deserialize, Serialize};
+
+/// The header carrying the token returned when the session was started.
+pub const SESSION_TOKEN_HEADER: &str = "X-Session-Token";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct NewSession {
+    category: String,
+    count: Option<usize>,
+}
+
+const MAX_PLAYER_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct NewAnswer {
+    question_id: i64,
+    choice_id: i64,
+}
+
+/**
+ * A session as returned to the player.
+ *
+ * The questions and the token are only included when the session starts; the token is required
+ * to read or answer the session afterwards.
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionProgress {
+    #[serde(flatten)]
+    pub session: Session,
+    pub total: usize,
+    pub answered: usize,
+    pub score: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub questions: Option<Vec<PlayerQuestion>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl SessionProgress {
+    fn new(session: Session, questions: Option<Vec<PlayerQuestion>>) -> SessionProgress {
+        SessionProgress {
+            total: session.answers.len(),
+            answered: session.answered(),
+            score: session.score(),
+            session,
+            questions,
+            token: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionAnswerResponse {
+    pub answer: AnswerResult,
+    pub session: SessionProgress,
+}
+
+fn session_id(event: &APIGatewayEvent) -> Result<i64, APIErrorResponse> {
+    match event.get_path_param("id") {
+        Some(id) => Ok(id),
+        None => Err(QueryParameterError {
+            parameter: "id".into(),
+            detail: Some("numeric session id required in path".into()),
+        }
+        .into()),
+    }
+}
+
+fn session_not_found(id: i64) -> APIErrorResponse {
+    NotFound {
+        detail: Some(format!("Session '{}' does not exist", id)),
+    }
+    .into()
+}
+
+/// 128 random bits, so that tokens can not be guessed like the sequential session ids.
+fn new_token() -> Result<String, APIErrorResponse> {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| {
+        APIErrorResponse::error(
+            500,
+            "session.token".into(),
+            "Session Token Error".into(),
+            Some("Failed to generate a session token".into()),
+            None,
+        )
+    })?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn token_hash(token: &str) -> String {
+    let digest = digest::digest(&digest::SHA256, token.as_bytes());
+    URL_SAFE_NO_PAD.encode(digest.as_ref())
+}
+
+/**
+ * Loads the session identified by the path, if the request carries its token.
+ *
+ * A wrong token is reported like a missing session, so that ids can not be probed.
+ */
+fn authorized_session(
+    event: &APIGatewayEvent,
+    config: &Config,
+) -> Result<Session, APIErrorResponse> {
+    let id = session_id(event)?;
+    let token = event.header(SESSION_TOKEN_HEADER).ok_or_else(|| {
+        APIErrorResponse::from(Unauthorized {
+            detail: Some(format!("'{}' header required", SESSION_TOKEN_HEADER)),
+        })
+    })?;
+
+    match config.sessions.get_session(id)? {
+        Some(session) if session.token_hash == Some(token_hash(token)) => Ok(session),
+        _ => Err(session_not_found(id)),
+    }
+}
+
+fn already_answered(question_id: i64) -> APIErrorResponse {
+    Conflict {
+        detail: Some(format!("Question '{}' was already answered", question_id)),
+    }
+    .into()
+}
+
+/// Starts a session with questions sampled from the category.
+pub fn start_session_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let new_session = match event.parse::<NewSession>() {
+        Ok(Some(new_session)) => new_session,
+        Ok(None) => {
+            return Err(BodyParameterError {
+                pointer: "".into(),
+                detail: Some("Expected {\"category\": \"<name>\", \"count\": <count> }".into()),
+            }
+            .into())
+        }
+        Err(e) => return Err(e),
+    };
+
+    let count = match new_session.count {
+        None => DEFAULT_COUNT,
+        Some(count) if (1..=MAX_COUNT).contains(&count) => count,
+        Some(_) => {
+            return Err(BodyParameterError {
+                pointer: "/count".into(),
+                detail: Some(format!("'count' must be between 1 and {}", MAX_COUNT)),
+            }
+            .into())
+        }
+    };
+
+    // Players are ranked under their authenticated principal, never a name from the body, so that
+    // nobody can post scores under another player's name. Anonymous sessions are not ranked.
+    let player = config.principal(&event)?;
+    if player
+        .as_ref()
+        .is_some_and(|player| player.chars().count() > MAX_PLAYER_LENGTH)
+    {
+        return Err(ValidationError {
+            detail: Some(format!(
+                "Player names are limited to {} characters",
+                MAX_PLAYER_LENGTH
+            )),
+        }
+        .into());
+    }
+
+    let questions =
+        config
+            .questions
+            .sample_questions(&new_session.category, count, random_seed())?;
+    if questions.is_empty() {
+        return Err(ValidationError {
+            detail: Some(format!(
+                "Category '{}' has no questions",
+                new_session.category
+            )),
+        }
+        .into());
+    }
+
+    let token = new_token()?;
+    let question_ids: Vec<i64> = questions.iter().filter_map(|q| q.id).collect();
+    let session = config.sessions.create_session(&Session {
+        player,
+        token_hash: Some(token_hash(&token)),
+        ..Session::new(&new_session.category, &question_ids, Utc::now())
+    })?;
+
+    let progress = SessionProgress {
+        token: Some(token),
+        ..SessionProgress::new(
+            session,
+            Some(questions.into_iter().map(PlayerQuestion::from).collect()),
+        )
+    };
+    Ok(APIGatewayResponse::new(201, Some(&progress)).unwrap())
+}
+
+/// Checks and records the answer to one of the questions of the session.
+pub fn answer_session_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let session = authorized_session(&event, &config)?;
+    let id = session.id.unwrap_or_default();
+
+    let new_answer = match event.parse::<NewAnswer>() {
+        Ok(Some(new_answer)) => new_answer,
+        Ok(None) => {
+            return Err(BodyParameterError {
+                pointer: "".into(),
+                detail: Some(
+                    "Expected {\"question_id\": <question id>, \"choice_id\": <choice id> }".into(),
+                ),
+            }
+            .into())
+        }
+        Err(e) => return Err(e),
+    };
+
+    match session
+        .answers
+        .iter()
+        .find(|answer| answer.question_id == new_answer.question_id)
+    {
+        Some(answer) if answer.answered_at.is_some() => {
+            return Err(already_answered(new_answer.question_id))
+        }
+        Some(_) => {}
+        None => {
+            return Err(BodyParameterError {
+                pointer: "/question_id".into(),
+                detail: Some(format!(
+                    "Question '{}' is not part of session '{}'",
+                    new_answer.question_id, id
+                )),
+            }
+            .into())
+        }
+    }
+
+    let result = config
+        .questions
+        .get_question(new_answer.question_id)?
+        .and_then(|question| question.check_answer(new_answer.choice_id))
+        .ok_or_else(|| {
+            APIErrorResponse::from(BodyParameterError {
+                pointer: "/choice_id".into(),
+                detail: Some(format!(
+                    "Choice '{}' does not belong to question '{}'",
+                    new_answer.choice_id, new_answer.question_id
+                )),
+            })
+        })?;
+
+    let answered_at = Utc::now();
+    let recorded = config.sessions.record_answer(
+        id,
+        &SessionAnswer {
+            question_id: new_answer.question_id,
+            choice_id: Some(new_answer.choice_id),
+            correct: Some(result.correct),
+            answered_at: Some(answered_at),
+            time_taken_ms: Some(session.time_since_last_answer(answered_at)),
+        },
+    )?;
+    if !recorded {
+        return Err(already_answered(new_answer.question_id));
+    }
+
+    let session = match config.sessions.get_session(id)? {
+        Some(session) => session,
+        None => return Err(session_not_found(id)),
+    };
+
+    if let Some(score) = Score::from_session(&session) {
+        config.leaderboards.record_score(&score)?;
+    }
+
+    let response = SessionAnswerResponse {
+        answer: result,
+        session: SessionProgress::new(session, None),
+    };
+    Ok(APIGatewayResponse::new(200, Some(&response)).unwrap())
+}
+
+/// Returns the progress of the session and, once completed, its final score.
+pub fn get_session_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let session = authorized_session(&event, &config)?;
+
+    let progress = SessionProgress::new(session, None);
+    Ok(APIGatewayResponse::new(200, Some(&progress)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::in_memory_config;
+    use http::StatusCode;
+    use models::{Category, Choice, Question};
+    use std::collections::HashMap;
+
+    fn event(id: Option<i64>, body: &str) -> APIGatewayEvent {
+        APIGatewayEvent {
+            path: "/sessions".into(),
+            path_parameters: id.map(|id| {
+                let mut path_parameters = HashMap::new();
+                path_parameters.insert("id".to_string(), id.to_string());
+                path_parameters
+            }),
+            body: Some(body.into()),
+            ..Default::default()
+        }
+    }
+
+    fn session_event(progress: &SessionProgress, body: &str) -> APIGatewayEvent {
+        with_token(
+            event(progress.session.id, body),
+            progress.token.as_ref().unwrap(),
+        )
+    }
+
+    fn with_token(event: APIGatewayEvent, token: &str) -> APIGatewayEvent {
+        let mut headers = HashMap::new();
+        headers.insert(SESSION_TOKEN_HEADER.to_string(), token.to_string());
+        APIGatewayEvent {
+            headers: Some(headers),
+            ..event
+        }
+    }
+
+    fn config_with_questions(count: usize) -> Config {
+        let config = in_memory_config();
+        let _ = config
+            .categories
+            .save_category_and_set_active(&Category::new("Joke"), Some(true));
+        for i in 0..count {
+            let _ = config.questions.save_question(&Question {
+                id: None,
+                question: format!("Joke {}", i),
+                category: "Joke".into(),
+                choices: vec![
+                    Choice {
+                        id: None,
+                        title: "Punchline".into(),
+                        correct: true,
+                    },
+                    Choice {
+                        id: None,
+                        title: "Silence".into(),
+                        correct: false,
+                    },
+                ],
+                difficulty: Default::default(),
+                tags: vec![],
+                updated_at: None,
+            });
+        }
+        config
+    }
+
+    fn start(config: &Config, count: usize) -> SessionProgress {
+        let body = format!("{{\"category\": \"Joke\", \"count\": {}}}", count);
+        let resp = start_session_handler(event(None, &body), config.clone()).unwrap();
+        assert_eq!(resp.status_code, StatusCode::CREATED);
+        resp.parse().unwrap()
+    }
+
+    fn answer(
+        config: &Config,
+        progress: &SessionProgress,
+        index: usize,
+        correct: bool,
+    ) -> Result<APIGatewayResponse, APIErrorResponse> {
+        let question = &progress.questions.as_ref().unwrap()[index];
+        let choice = match correct {
+            true => &question.choices[0],
+            false => &question.choices[1],
+        };
+        let body = format!(
+            "{{\"question_id\": {}, \"choice_id\": {}}}",
+            question.id.unwrap(),
+            choice.id.unwrap()
+        );
+        answer_session_handler(session_event(progress, &body), config.clone())
+    }
+
+    #[test]
+    fn test_start_session_hides_correct_choices() {
+        let config = config_with_questions(5);
+
+        let progress = start(&config, 3);
+
+        assert!(progress.session.id.is_some());
+        assert!(progress.token.is_some());
+        assert_eq!(progress.total, 3);
+        assert_eq!(progress.answered, 0);
+        assert_eq!(progress.questions.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_start_session_without_questions_returns_400() {
+        let config = in_memory_config();
+
+        match start_session_handler(event(None, r#"{"category": "Joke"}"#), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_answers_are_scored_until_completed() {
+        let config = config_with_questions(5);
+        let progress = start(&config, 2);
+
+        let first: SessionAnswerResponse = answer(&config, &progress, 0, true)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(first.answer.correct);
+        assert_eq!(first.session.answered, 1);
+        assert!(first.session.session.completed_at.is_none());
+
+        let second: SessionAnswerResponse = answer(&config, &progress, 1, false)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(!second.answer.correct);
+        assert!(second.session.session.completed_at.is_some());
+
+        let resp = get_session_handler(session_event(&progress, ""), config.clone()).unwrap();
+        let final_progress: SessionProgress = resp.parse().unwrap();
+        assert_eq!(final_progress.score, 1);
+        assert_eq!(final_progress.answered, 2);
+        assert!(final_progress
+            .session
+            .answers
+            .iter()
+            .all(|answer| answer.time_taken_ms.is_some()));
+    }
+
+    #[test]
+    fn test_answering_twice_returns_409() {
+        let config = config_with_questions(5);
+        let progress = start(&config, 2);
+
+        answer(&config, &progress, 0, true).unwrap();
+        match answer(&config, &progress, 0, false) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::CONFLICT),
+        }
+    }
+
+    #[test]
+    fn test_question_outside_session_returns_400() {
+        let config = config_with_questions(5);
+        let progress = start(&config, 1);
+
+        let body = r#"{"question_id": 999, "choice_id": 1}"#;
+        match answer_session_handler(session_event(&progress, body), config) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::BAD_REQUEST),
+        }
+    }
+
+    #[test]
+    fn test_missing_session_returns_404() {
+        match get_session_handler(with_token(event(Some(42), ""), "token"), in_memory_config()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+    }
+
+    #[test]
+    fn test_sessions_require_their_token() {
+        let config = config_with_questions(5);
+        let progress = start(&config, 2);
+        let other = start(&config, 2);
+        assert_ne!(progress.token, other.token);
+
+        match get_session_handler(event(progress.session.id, ""), config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::UNAUTHORIZED),
+        }
+
+        let stolen = with_token(
+            event(progress.session.id, ""),
+            other.token.as_ref().unwrap(),
+        );
+        match get_session_handler(stolen, config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+
+        let question = &progress.questions.as_ref().unwrap()[0];
+        let body = format!(
+            "{{\"question_id\": {}, \"choice_id\": {}}}",
+            question.id.unwrap(),
+            question.choices[0].id.unwrap()
+        );
+        let stolen = with_token(
+            event(progress.session.id, &body),
+            other.token.as_ref().unwrap(),
+        );
+        match answer_session_handler(stolen, config.clone()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), StatusCode::NOT_FOUND),
+        }
+
+        let resp = get_session_handler(session_event(&progress, ""), config).unwrap();
+        let progress: SessionProgress = resp.parse().unwrap();
+        assert_eq!(progress.answered, 0);
+        assert!(progress.token.is_none());
+    }
+}
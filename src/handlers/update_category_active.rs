@@ -0,0 +1,88 @@
+use apigateway::{APIErrorType::*, *};
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CategoryStatus {
+    active: bool,
+}
+
+pub fn update_category_active_handler(
+    event: APIGatewayEvent,
+    config: Config,
+) -> Result<APIGatewayResponse, APIErrorResponse> {
+    let category: String = match event.get_path_param("category") {
+        Some(category) => category,
+        None => {
+            return Err(QueryParameterError {
+                parameter: "category".into(),
+                detail: Some("category required in path".into()),
+            }
+            .into())
+        }
+    };
+    let status = match event.parse::<CategoryStatus>() {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            return Err(BodyParameterError {
+                pointer: "".into(),
+                detail: Some("Expected {\"active\": [true|false] }".into()),
+            }
+            .into())
+        }
+        Err(e) => return Err(e),
+    };
+
+    let active = config
+        .categories
+        .set_category_active(&category, status.active)?;
+    let api_response = APIGatewayResponse::new(200, Some(&CategoryStatus { active })).unwrap();
+
+    Ok(api_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use in_memory::InMemoryStore;
+    use models::Category;
+    use repositories::CategoryStore;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_update_category_active_returns_204_with_status_active() {
+        let title = "Science".to_string();
+
+        let mut path_params: HashMap<String, String> = HashMap::new();
+        path_params.insert("category".into(), title.clone());
+        path_params.insert("active".into(), "true".into());
+
+        let event = APIGatewayEvent {
+            path: "/category/Science/activate".into(),
+            query: None,
+            path_parameters: Some(path_params),
+            body: Some("{\"active\": true }".into()),
+            ..Default::default()
+        };
+
+        let store = Rc::new(InMemoryStore::new());
+        let config = Config::new(store.clone(), store.clone(), store.clone(), store.clone());
+        let _ = config
+            .categories
+            .save_category(&Category::new(title.clone()));
+
+        match update_category_active_handler(event, config) {
+            Err(e) => panic!("{:?}", e),
+            Ok(resp) => {
+                assert_eq!(resp.status_code, 200);
+
+                let category_status = resp.parse::<CategoryStatus>().unwrap();
+                assert!(category_status.active);
+
+                let categories = store.list_categories().unwrap().categories;
+                assert_eq!(categories.iter().filter(|c| c.title == title).count(), 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,1048 @@
+use chrono::{DateTime, Utc};
+use models::{
+    normalize_tags, Categories, Category, CategorySummaries, CategorySummary, Choice,
+    LeaderboardEntry, Question, QuestionFilter, Score, Session, SessionAnswer,
+};
+use repositories::{
+    category_in_use, CategoryStore, LeaderboardStore, QuestionStore, RepositoryError,
+    SaveCategoryStatus, SessionStore,
+};
+use sampling::sample;
+use std::cell::RefCell;
+
+struct CategoryRow {
+    name: String,
+    active: bool,
+    description: Option<String>,
+    icon_url: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Tables {
+    categories: Vec<CategoryRow>,
+    questions: Vec<Question>,
+    sessions: Vec<Session>,
+    scores: Vec<Score>,
+    question_sequence: i64,
+    choice_sequence: i64,
+    session_sequence: i64,
+}
+
+impl Tables {
+    fn is_active(&self, category: &str) -> bool {
+        self.categories
+            .iter()
+            .any(|row| row.name == category && row.active)
+    }
+
+    fn has_category(&self, category: &str) -> bool {
+        self.categories.iter().any(|row| row.name == category)
+    }
+
+    /// The best score of each player in the category since `since`, ranked.
+    fn best_scores(&self, category: &str, since: Option<DateTime<Utc>>) -> Vec<&Score> {
+        let mut scores: Vec<&Score> = self
+            .scores
+            .iter()
+            .filter(|score| score.category == category)
+            .filter(|score| since.is_none_or(|since| score.completed_at >= since))
+            .collect();
+        scores.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(a.completed_at.cmp(&b.completed_at))
+                .then(a.player.cmp(&b.player))
+        });
+
+        let mut players: Vec<&str> = vec![];
+        scores.retain(|score| {
+            if players.contains(&score.player.as_str()) {
+                return false;
+            }
+            players.push(&score.player);
+            true
+        });
+        scores
+    }
+
+    /// Copies the choices, assigning ids from the choice sequence.
+    fn insert_choices(&mut self, choices: &[Choice]) -> Vec<Choice> {
+        let mut inserted = Vec::with_capacity(choices.len());
+        for choice in choices.iter() {
+            self.choice_sequence += 1;
+            inserted.push(Choice {
+                id: Some(self.choice_sequence),
+                title: choice.title.clone(),
+                correct: choice.correct,
+            });
+        }
+        inserted
+    }
+}
+
+fn foreign_key_violation() -> RepositoryError {
+    RepositoryError::DatabaseError(
+        "23503".into(),
+        "insert or update on table \"questions\" violates foreign key constraint \"questions_category_fkey\"".into(),
+    )
+}
+
+fn question_in_session() -> RepositoryError {
+    RepositoryError::DatabaseError(
+        "23503".into(),
+        "update or delete on table \"questions\" violates foreign key constraint \"quiz_session_answers_question_id_fkey\"".into(),
+    )
+}
+
+/**
+ * Implements `CategoryStore` and `QuestionStore` without a database.
+ *
+ * The store mirrors the behaviour of the Postgres repositories: ids are assigned from sequences
+ * starting at 1, rows are returned in insertion order, questions require an existing category
+ * (like the foreign key on `questions.category`) and questions in inactive categories are hidden.
+ *
+ * A single instance should be shared (through an `Rc`) as both the category and question store.
+ */
+#[derive(Default)]
+pub struct InMemoryStore {
+    tables: RefCell<Tables>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> InMemoryStore {
+        InMemoryStore::default()
+    }
+}
+
+impl CategoryStore for InMemoryStore {
+    fn save_category_and_set_active(
+        &self,
+        category: &Category,
+        active: Option<bool>,
+    ) -> Result<SaveCategoryStatus, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        if let Some(row) = tables
+            .categories
+            .iter_mut()
+            .find(|row| row.name == category.title)
+        {
+            // ON CONFLICT(name) DO UPDATE counts as an affected row, ON CONFLICT DO NOTHING does not.
+            return Ok(match active {
+                Some(active) => {
+                    row.active = active;
+                    row.updated_at = Utc::now();
+                    SaveCategoryStatus::Created
+                }
+                None => SaveCategoryStatus::Exists,
+            });
+        }
+
+        tables.categories.push(CategoryRow {
+            name: category.title.clone(),
+            active: active.unwrap_or(false),
+            description: category.description.clone(),
+            icon_url: category.icon_url.clone(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        Ok(SaveCategoryStatus::Created)
+    }
+
+    fn list_categories(&self) -> Result<Categories, RepositoryError> {
+        let tables = self.tables.borrow();
+        let categories = tables
+            .categories
+            .iter()
+            .filter(|row| row.active)
+            .map(|row| {
+                let questions: Vec<&Question> = tables
+                    .questions
+                    .iter()
+                    .filter(|question| question.category == row.name)
+                    .collect();
+                Category {
+                    title: row.name.clone(),
+                    question_count: Some(questions.len() as i64),
+                    description: row.description.clone(),
+                    icon_url: row.icon_url.clone(),
+                    created_at: Some(row.created_at),
+                    updated_at: questions
+                        .iter()
+                        .filter_map(|question| question.updated_at)
+                        .chain(Some(row.updated_at))
+                        .max(),
+                }
+            })
+            .collect();
+
+        Ok(Categories { categories })
+    }
+
+    fn set_category_active(&self, name: &str, active: bool) -> Result<bool, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+        match tables
+            .categories
+            .iter_mut()
+            .find(|row| row.name == name && row.active != active)
+        {
+            Some(row) => {
+                row.active = active;
+                row.updated_at = Utc::now();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn list_category_summaries(&self) -> Result<CategorySummaries, RepositoryError> {
+        let tables = self.tables.borrow();
+        let mut categories: Vec<CategorySummary> = tables
+            .categories
+            .iter()
+            .map(|row| CategorySummary {
+                title: row.name.clone(),
+                active: row.active,
+                question_count: tables
+                    .questions
+                    .iter()
+                    .filter(|question| question.category == row.name)
+                    .count() as i64,
+            })
+            .collect();
+        categories.sort_by(|a, b| a.title.cmp(&b.title));
+
+        Ok(CategorySummaries { categories })
+    }
+
+    fn rename_category(&self, name: &str, new_name: &str) -> Result<bool, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        if !tables.has_category(name) {
+            return Ok(false);
+        }
+        if name != new_name && tables.has_category(new_name) {
+            return Err(RepositoryError::DatabaseError(
+                "23505".into(),
+                "duplicate key value violates unique constraint \"categories_pkey\"".into(),
+            ));
+        }
+
+        for row in tables.categories.iter_mut().filter(|row| row.name == name) {
+            row.name = new_name.to_string();
+            row.updated_at = Utc::now();
+        }
+        for question in tables
+            .questions
+            .iter_mut()
+            .filter(|question| question.category == name)
+        {
+            question.category = new_name.to_string();
+        }
+        for session in tables
+            .sessions
+            .iter_mut()
+            .filter(|session| session.category == name)
+        {
+            session.category = new_name.to_string();
+        }
+        for score in tables
+            .scores
+            .iter_mut()
+            .filter(|score| score.category == name)
+        {
+            score.category = new_name.to_string();
+        }
+        Ok(true)
+    }
+
+    fn delete_category(&self, name: &str, cascade: bool) -> Result<bool, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        if !tables.has_category(name) {
+            return Ok(false);
+        }
+
+        let in_use = tables
+            .questions
+            .iter()
+            .any(|question| question.category == name)
+            || tables
+                .sessions
+                .iter()
+                .any(|session| session.category == name);
+        if in_use && !cascade {
+            return Err(category_in_use(name));
+        }
+
+        let played_elsewhere = tables
+            .sessions
+            .iter()
+            .filter(|session| session.category != name)
+            .flat_map(|session| session.answers.iter())
+            .any(|answer| {
+                tables.questions.iter().any(|question| {
+                    question.id == Some(answer.question_id) && question.category == name
+                })
+            });
+        if played_elsewhere {
+            return Err(question_in_session());
+        }
+
+        tables.categories.retain(|row| row.name != name);
+        tables
+            .questions
+            .retain(|question| question.category != name);
+        tables.sessions.retain(|session| session.category != name);
+        tables.scores.retain(|score| score.category != name);
+        Ok(true)
+    }
+}
+
+impl QuestionStore for InMemoryStore {
+    fn save_question(&self, question: &Question) -> Result<Question, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        if !tables.has_category(&question.category) {
+            return Err(foreign_key_violation());
+        }
+
+        tables.question_sequence += 1;
+        let question_id = tables.question_sequence;
+        let choices = tables.insert_choices(&question.choices);
+
+        let saved = Question {
+            id: Some(question_id),
+            question: question.question.clone(),
+            category: question.category.clone(),
+            choices,
+            difficulty: question.difficulty,
+            tags: normalize_tags(&question.tags),
+            updated_at: Some(Utc::now()),
+        };
+        tables.questions.push(saved.clone());
+
+        Ok(saved)
+    }
+
+    fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, RepositoryError> {
+        let tables = self.tables.borrow();
+        Ok(tables
+            .questions
+            .iter()
+            .filter(|question| tables.is_active(&question.category) && filter.matches(question))
+            .count() as i64)
+    }
+
+    fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        page: i64,
+        size: i64,
+    ) -> Result<Vec<Question>, RepositoryError> {
+        let tables = self.tables.borrow();
+        let offset = match page {
+            0 => 0i64,
+            _ => (page - 1i64) * size,
+        };
+
+        Ok(tables
+            .questions
+            .iter()
+            .filter(|question| tables.is_active(&question.category) && filter.matches(question))
+            .skip(offset.max(0) as usize)
+            .take(size.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn sample_questions(
+        &self,
+        category: &str,
+        count: usize,
+        seed: u64,
+    ) -> Result<Vec<Question>, RepositoryError> {
+        let tables = self.tables.borrow();
+        if !tables.is_active(category) {
+            return Ok(vec![]);
+        }
+
+        let questions: Vec<&Question> = tables
+            .questions
+            .iter()
+            .filter(|question| question.category == category)
+            .collect();
+
+        Ok(sample(&questions, count, seed)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    fn get_question(&self, id: i64) -> Result<Option<Question>, RepositoryError> {
+        let tables = self.tables.borrow();
+        Ok(tables
+            .questions
+            .iter()
+            .find(|question| question.id == Some(id))
+            .cloned())
+    }
+
+    fn get_active_question(&self, id: i64) -> Result<Option<Question>, RepositoryError> {
+        let tables = self.tables.borrow();
+        Ok(tables
+            .questions
+            .iter()
+            .find(|question| question.id == Some(id) && tables.is_active(&question.category))
+            .cloned())
+    }
+
+    fn update_question(
+        &self,
+        id: i64,
+        question: &Question,
+    ) -> Result<Option<Question>, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        let index = match tables
+            .questions
+            .iter()
+            .position(|question| question.id == Some(id))
+        {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        if !tables.has_category(&question.category) {
+            return Err(foreign_key_violation());
+        }
+
+        let updated = Question {
+            id: Some(id),
+            question: question.question.clone(),
+            category: question.category.clone(),
+            choices: tables.insert_choices(&question.choices),
+            difficulty: question.difficulty,
+            tags: normalize_tags(&question.tags),
+            updated_at: Some(Utc::now()),
+        };
+        tables.questions[index] = updated.clone();
+
+        Ok(Some(updated))
+    }
+
+    fn delete_question(&self, id: i64) -> Result<bool, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+        let in_session = tables.sessions.iter().any(|session| {
+            session
+                .answers
+                .iter()
+                .any(|answer| answer.question_id == id)
+        });
+        if in_session {
+            return Err(question_in_session());
+        }
+
+        let count = tables.questions.len();
+        tables.questions.retain(|question| question.id != Some(id));
+        Ok(tables.questions.len() < count)
+    }
+}
+
+impl SessionStore for InMemoryStore {
+    fn create_session(&self, session: &Session) -> Result<Session, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        if !tables.has_category(&session.category) {
+            return Err(foreign_key_violation());
+        }
+
+        tables.session_sequence += 1;
+        let saved = Session {
+            id: Some(tables.session_sequence),
+            ..session.clone()
+        };
+        tables.sessions.push(saved.clone());
+
+        Ok(saved)
+    }
+
+    fn get_session(&self, id: i64) -> Result<Option<Session>, RepositoryError> {
+        let tables = self.tables.borrow();
+        Ok(tables
+            .sessions
+            .iter()
+            .find(|session| session.id == Some(id))
+            .cloned())
+    }
+
+    fn record_answer(
+        &self,
+        session_id: i64,
+        answer: &SessionAnswer,
+    ) -> Result<bool, RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        let session = match tables
+            .sessions
+            .iter_mut()
+            .find(|session| session.id == Some(session_id))
+        {
+            Some(session) => session,
+            None => return Ok(false),
+        };
+
+        match session.answers.iter_mut().find(|unanswered| {
+            unanswered.question_id == answer.question_id && unanswered.answered_at.is_none()
+        }) {
+            Some(unanswered) => *unanswered = answer.clone(),
+            None => return Ok(false),
+        }
+
+        if session.answered() == session.answers.len() {
+            session.completed_at = answer.answered_at;
+        }
+        Ok(true)
+    }
+}
+
+impl LeaderboardStore for InMemoryStore {
+    fn record_score(&self, score: &Score) -> Result<(), RepositoryError> {
+        let mut tables = self.tables.borrow_mut();
+
+        if !tables.has_category(&score.category) {
+            return Err(foreign_key_violation());
+        }
+        if !tables
+            .scores
+            .iter()
+            .any(|recorded| recorded.session_id == score.session_id)
+        {
+            tables.scores.push(score.clone());
+        }
+        Ok(())
+    }
+
+    fn count_players(
+        &self,
+        category: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<i64, RepositoryError> {
+        let tables = self.tables.borrow();
+        Ok(tables.best_scores(category, since).len() as i64)
+    }
+
+    fn get_leaderboard(
+        &self,
+        category: &str,
+        since: Option<DateTime<Utc>>,
+        page: i64,
+        size: i64,
+    ) -> Result<Vec<LeaderboardEntry>, RepositoryError> {
+        let tables = self.tables.borrow();
+
+        let offset = match page {
+            0 => 0i64,
+            _ => (page - 1i64) * size,
+        };
+
+        Ok(tables
+            .best_scores(category, since)
+            .into_iter()
+            .enumerate()
+            .skip(offset.max(0) as usize)
+            .take(size.max(0) as usize)
+            .map(|(index, score)| LeaderboardEntry {
+                rank: index as i64 + 1,
+                player: score.player.clone(),
+                score: score.score,
+                total: score.total,
+                completed_at: score.completed_at,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::TagMatch;
+
+    fn question(category: &str, text: &str) -> Question {
+        Question {
+            id: None,
+            question: text.into(),
+            category: category.into(),
+            choices: vec![
+                Choice {
+                    id: None,
+                    title: "Yes".into(),
+                    correct: true,
+                },
+                Choice {
+                    id: None,
+                    title: "No".into(),
+                    correct: false,
+                },
+            ],
+            difficulty: Default::default(),
+            tags: vec![],
+            updated_at: None,
+        }
+    }
+
+    fn category(title: &str) -> Category {
+        Category::new(title)
+    }
+
+    #[test]
+    fn test_save_category_does_nothing_on_conflict() {
+        let store = InMemoryStore::new();
+
+        match store.save_category(&category("Science")).unwrap() {
+            SaveCategoryStatus::Created => {}
+            status => panic!("Expected Created, got {:?}", status),
+        }
+        match store.save_category(&category("Science")).unwrap() {
+            SaveCategoryStatus::Exists => {}
+            status => panic!("Expected Exists, got {:?}", status),
+        }
+        assert!(store.list_categories().unwrap().categories.is_empty());
+    }
+
+    #[test]
+    fn test_list_categories_includes_metadata() {
+        let store = InMemoryStore::new();
+        store
+            .save_category_and_set_active(
+                &Category {
+                    description: Some("Physics, chemistry and biology".into()),
+                    ..Category::new("Science")
+                },
+                Some(true),
+            )
+            .unwrap();
+        store.save_question(&question("Science", "First")).unwrap();
+        store.save_question(&question("Science", "Second")).unwrap();
+
+        let categories = store.list_categories().unwrap().categories;
+        assert_eq!(categories[0].question_count, Some(2));
+        assert_eq!(
+            categories[0].description,
+            Some("Physics, chemistry and biology".into())
+        );
+        assert_eq!(categories[0].icon_url, None);
+        assert!(categories[0].created_at.is_some());
+    }
+
+    #[test]
+    fn test_save_category_and_set_active_updates_on_conflict() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+
+        match store
+            .save_category_and_set_active(&category("Science"), Some(true))
+            .unwrap()
+        {
+            SaveCategoryStatus::Created => {}
+            status => panic!("Expected Created, got {:?}", status),
+        }
+
+        let categories = store.list_categories().unwrap().categories;
+        assert_eq!(categories.len(), 1);
+        assert_eq!(categories[0].title, "Science");
+    }
+
+    #[test]
+    fn test_set_category_active_returns_false_when_unchanged() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+
+        assert!(!store.set_category_active("Science", false).unwrap());
+        assert!(store.set_category_active("Science", true).unwrap());
+        assert!(!store.set_category_active("Science", true).unwrap());
+        assert!(!store.set_category_active("History", true).unwrap());
+    }
+
+    #[test]
+    fn test_rename_category_moves_questions() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        store.save_category(&category("History")).unwrap();
+        store.save_question(&question("Science", "First")).unwrap();
+
+        assert!(store.rename_category("Science", "Physics").unwrap());
+        assert!(!store.rename_category("Science", "Physics").unwrap());
+        match store.rename_category("Physics", "History") {
+            Err(RepositoryError::DatabaseError(code, _)) => assert_eq!(code, "23505"),
+            result => panic!("Expected unique violation, got {:?}", result),
+        }
+
+        let summaries = store.list_category_summaries().unwrap().categories;
+        assert_eq!(
+            summaries,
+            vec![
+                CategorySummary {
+                    title: "History".into(),
+                    active: false,
+                    question_count: 0,
+                },
+                CategorySummary {
+                    title: "Physics".into(),
+                    active: false,
+                    question_count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delete_category_deletes_questions_only_with_cascade() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        let saved = store.save_question(&question("Science", "First")).unwrap();
+
+        match store.delete_category("Science", false) {
+            Err(RepositoryError::DatabaseError(code, _)) => assert_eq!(code, "23503"),
+            result => panic!("Expected foreign key violation, got {:?}", result),
+        }
+        assert!(store.get_question(saved.id.unwrap()).unwrap().is_some());
+
+        assert!(store.delete_category("Science", true).unwrap());
+        assert!(!store.delete_category("Science", true).unwrap());
+        assert!(store.get_question(saved.id.unwrap()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_save_question_assigns_ids() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+
+        let first = store.save_question(&question("Science", "First")).unwrap();
+        let second = store.save_question(&question("Science", "Second")).unwrap();
+
+        assert_eq!(first.id, Some(1));
+        assert_eq!(second.id, Some(2));
+        let choice_ids: Vec<Option<i64>> = first
+            .choices
+            .iter()
+            .chain(second.choices.iter())
+            .map(|choice| choice.id)
+            .collect();
+        assert_eq!(choice_ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn test_save_question_requires_category() {
+        let store = InMemoryStore::new();
+
+        match store.save_question(&question("Science", "First")) {
+            Err(RepositoryError::DatabaseError(code, _)) => assert_eq!(code, "23503"),
+            result => panic!("Expected foreign key violation, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_questions_in_inactive_categories_are_hidden() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        store.save_question(&question("Science", "First")).unwrap();
+
+        assert_eq!(
+            store
+                .count_questions(&QuestionFilter::category("Science"))
+                .unwrap(),
+            0
+        );
+        assert!(store
+            .get_questions(&QuestionFilter::category("Science"), 1, 10)
+            .unwrap()
+            .is_empty());
+
+        store.set_category_active("Science", true).unwrap();
+
+        assert_eq!(
+            store
+                .count_questions(&QuestionFilter::category("Science"))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store
+                .get_questions(&QuestionFilter::category("Science"), 1, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_get_questions_paginates() {
+        let store = InMemoryStore::new();
+        store
+            .save_category_and_set_active(&category("Science"), Some(true))
+            .unwrap();
+        store
+            .save_category_and_set_active(&category("History"), Some(true))
+            .unwrap();
+        for i in 0..5 {
+            store
+                .save_question(&question("Science", &format!("Question {}", i)))
+                .unwrap();
+            store
+                .save_question(&question("History", &format!("Question {}", i)))
+                .unwrap();
+        }
+
+        let texts = |page: i64| -> Vec<String> {
+            store
+                .get_questions(&QuestionFilter::category("Science"), page, 2)
+                .unwrap()
+                .into_iter()
+                .map(|question| question.question)
+                .collect()
+        };
+
+        assert_eq!(
+            store
+                .count_questions(&QuestionFilter::category("Science"))
+                .unwrap(),
+            5
+        );
+        assert_eq!(texts(0), vec!["Question 0", "Question 1"]);
+        assert_eq!(texts(1), vec!["Question 0", "Question 1"]);
+        assert_eq!(texts(3), vec!["Question 4"]);
+        assert!(texts(4).is_empty());
+    }
+
+    #[test]
+    fn test_tags_cut_across_categories() {
+        let store = InMemoryStore::new();
+        for name in &["Science", "History", "Art"] {
+            store
+                .save_category_and_set_active(&category(name), Some(name != &"Art"))
+                .unwrap();
+        }
+        let tagged = |category: &str, text: &str, tags: &[&str]| {
+            let mut question = question(category, text);
+            question.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            store.save_question(&question).unwrap()
+        };
+        let saved = tagged("Science", "Apollo 11", &[" Space ", "SPACE", "Moon"]);
+        tagged("History", "Sputnik", &["space", "cold war"]);
+        tagged("History", "Berlin Wall", &["cold war"]);
+        tagged("Art", "Starry Night", &["space"]);
+
+        assert_eq!(saved.tags, vec!["moon", "space"]);
+
+        let texts = |filter: QuestionFilter| -> Vec<String> {
+            assert_eq!(
+                store.count_questions(&filter).unwrap(),
+                store.get_questions(&filter, 1, 10).unwrap().len() as i64
+            );
+            store
+                .get_questions(&filter, 1, 10)
+                .unwrap()
+                .into_iter()
+                .map(|question| question.question)
+                .collect()
+        };
+        let tags =
+            |tags: &[&str]| -> Vec<String> { tags.iter().map(|tag| tag.to_string()).collect() };
+
+        assert_eq!(
+            texts(QuestionFilter::tags(&tags(&["Space"]), TagMatch::Any)),
+            vec!["Apollo 11", "Sputnik"]
+        );
+        assert_eq!(
+            texts(QuestionFilter::tags(
+                &tags(&["moon", "cold war"]),
+                TagMatch::Any
+            )),
+            vec!["Apollo 11", "Sputnik", "Berlin Wall"]
+        );
+        assert_eq!(
+            texts(QuestionFilter::tags(
+                &tags(&["space", "cold war"]),
+                TagMatch::All
+            )),
+            vec!["Sputnik"]
+        );
+        assert_eq!(
+            texts(QuestionFilter {
+                category: Some("History".into()),
+                ..QuestionFilter::tags(&tags(&["space"]), TagMatch::Any)
+            }),
+            vec!["Sputnik"]
+        );
+    }
+
+    #[test]
+    fn test_sample_questions_is_reproducible() {
+        let store = InMemoryStore::new();
+        store
+            .save_category_and_set_active(&category("Science"), Some(true))
+            .unwrap();
+        for i in 0..20 {
+            store
+                .save_question(&question("Science", &format!("Question {}", i)))
+                .unwrap();
+        }
+
+        let ids = |seed: u64| -> Vec<Option<i64>> {
+            store
+                .sample_questions("Science", 5, seed)
+                .unwrap()
+                .into_iter()
+                .map(|question| question.id)
+                .collect()
+        };
+
+        assert_eq!(ids(1).len(), 5);
+        assert_eq!(ids(1), ids(1));
+        assert_ne!(ids(1), ids(2));
+        assert!(store.sample_questions("History", 5, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_answer_completes_session() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        let started_at = Utc::now();
+        let session = store
+            .create_session(&Session::new("Science", &[1, 2], started_at))
+            .unwrap();
+        let session_id = session.id.unwrap();
+
+        let answer = |question_id: i64| SessionAnswer {
+            question_id,
+            choice_id: Some(1),
+            correct: Some(true),
+            answered_at: Some(started_at),
+            time_taken_ms: Some(0),
+        };
+
+        assert!(store.record_answer(session_id, &answer(1)).unwrap());
+        assert!(!store.record_answer(session_id, &answer(1)).unwrap());
+        assert!(!store.record_answer(session_id, &answer(3)).unwrap());
+        assert!(store
+            .get_session(session_id)
+            .unwrap()
+            .unwrap()
+            .completed_at
+            .is_none());
+
+        assert!(store.record_answer(session_id, &answer(2)).unwrap());
+        let session = store.get_session(session_id).unwrap().unwrap();
+        assert_eq!(session.completed_at, Some(started_at));
+        assert_eq!(session.score(), 2);
+    }
+
+    #[test]
+    fn test_leaderboard_ranks_best_score_per_player() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        let start = Utc::now() - chrono::Duration::days(3);
+
+        let score = |session_id: i64, player: &str, score: i64, days: i64| Score {
+            session_id,
+            player: player.into(),
+            category: "Science".into(),
+            score,
+            total: 10,
+            completed_at: start + chrono::Duration::days(days),
+        };
+        store.record_score(&score(1, "ada", 7, 0)).unwrap();
+        store.record_score(&score(2, "ada", 9, 2)).unwrap();
+        store.record_score(&score(3, "alan", 9, 1)).unwrap();
+        store.record_score(&score(4, "grace", 5, 3)).unwrap();
+        store.record_score(&score(4, "grace", 5, 3)).unwrap();
+
+        let ranking = |since: Option<DateTime<Utc>>| -> Vec<(i64, String, i64)> {
+            store
+                .get_leaderboard("Science", since, 1, 10)
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.rank, entry.player, entry.score))
+                .collect()
+        };
+
+        assert_eq!(
+            ranking(None),
+            vec![
+                (1, "alan".to_string(), 9),
+                (2, "ada".to_string(), 9),
+                (3, "grace".to_string(), 5),
+            ]
+        );
+        assert_eq!(store.count_players("Science", None).unwrap(), 3);
+        assert_eq!(
+            ranking(Some(start + chrono::Duration::days(2))),
+            vec![(1, "ada".to_string(), 9), (2, "grace".to_string(), 5)]
+        );
+        assert_eq!(
+            store.get_leaderboard("Science", None, 2, 2).unwrap()[0].rank,
+            3
+        );
+    }
+
+    #[test]
+    fn test_update_question_replaces_choices() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        store.save_category(&category("History")).unwrap();
+        let saved = store.save_question(&question("Science", "First")).unwrap();
+
+        let updated = store
+            .update_question(saved.id.unwrap(), &question("History", "Updated"))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(updated.id, saved.id);
+        assert_eq!(updated.category, "History");
+        let choice_ids: Vec<Option<i64>> = updated.choices.iter().map(|choice| choice.id).collect();
+        assert_eq!(choice_ids, vec![Some(3), Some(4)]);
+        assert_eq!(
+            store
+                .get_question(saved.id.unwrap())
+                .unwrap()
+                .unwrap()
+                .question,
+            "Updated"
+        );
+        assert!(store
+            .update_question(99, &question("History", "Missing"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_delete_question() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        let saved = store.save_question(&question("Science", "First")).unwrap();
+
+        assert!(store.delete_question(saved.id.unwrap()).unwrap());
+        assert!(!store.delete_question(saved.id.unwrap()).unwrap());
+        assert!(store.get_question(saved.id.unwrap()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delete_question_keeps_questions_of_sessions() {
+        let store = InMemoryStore::new();
+        store.save_category(&category("Science")).unwrap();
+        let saved = store.save_question(&question("Science", "First")).unwrap();
+        let id = saved.id.unwrap();
+        store
+            .create_session(&Session::new("Science", &[id], Utc::now()))
+            .unwrap();
+
+        match store.delete_question(id) {
+            Err(RepositoryError::DatabaseError(code, _)) => assert_eq!(code, "23503"),
+            result => panic!("Expected foreign key violation, got {:?}", result),
+        }
+        assert!(store.get_question(id).unwrap().is_some());
+    }
+}
@@ -0,0 +1,170 @@
+use serde_json::error::Category;
+
+/// Escapes a key or index for use as a reference token of a JSON pointer (RFC 6901).
+pub fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+enum Frame {
+    Object { key: Option<String>, in_value: bool },
+    Array { index: usize, has_value: bool },
+}
+
+/// The byte offset just past `column` on the 1-based `line`, as reported by `serde_json`.
+fn offset(json: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = json
+        .split('\n')
+        .take(line.saturating_sub(1))
+        .map(|line| line.len() + 1)
+        .sum();
+    (line_start + column).min(json.len())
+}
+
+/**
+ * The pointer to the innermost value being read at `line` and `column` of the document.
+ *
+ * `serde_json` reports errors at the position it had reached, which is the end of an invalid value,
+ * the closing brace of an object with a missing field, or wherever the syntax broke. The document
+ * is scanned up to that position, so the pointer is that of the value, the object or the member
+ * that was being read. Only the members read before the position are part of the pointer, so it
+ * resolves in any document that is valid JSON.
+ */
+pub fn pointer_at(json: &str, line: usize, column: usize) -> String {
+    let end = offset(json, line, column);
+    let mut stack: Vec<Frame> = vec![];
+    let mut chars = json[..end].chars();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        match (c, stack.last_mut()) {
+            ('}', _) | (']', _) => {
+                stack.pop();
+                continue;
+            }
+            (',', Some(Frame::Object { key, in_value })) => {
+                *key = None;
+                *in_value = false;
+                continue;
+            }
+            (',', Some(Frame::Array { index, .. })) => {
+                *index += 1;
+                continue;
+            }
+            (':', Some(Frame::Object { in_value, .. })) => {
+                *in_value = true;
+                continue;
+            }
+            (_, Some(Frame::Array { has_value, .. })) => *has_value = true,
+            _ => {}
+        }
+
+        match c {
+            '{' => stack.push(Frame::Object {
+                key: None,
+                in_value: false,
+            }),
+            '[' => stack.push(Frame::Array {
+                index: 0,
+                has_value: false,
+            }),
+            '"' => {
+                let mut string = String::new();
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => {
+                            escaped = true;
+                            continue;
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                    string.push(c);
+                }
+                if let Some(Frame::Object {
+                    key,
+                    in_value: false,
+                }) = stack.last_mut()
+                {
+                    *key = Some(string);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stack
+        .iter()
+        .filter_map(|frame| match frame {
+            Frame::Object { key: Some(key), .. } => Some(format!("/{}", escape(key))),
+            Frame::Array {
+                index,
+                has_value: true,
+            } => Some(format!("/{}", index)),
+            _ => None,
+        })
+        .collect()
+}
+
+/**
+ * The pointer to the value that `serde_json` failed to read from `json`.
+ *
+ * Syntax errors are reported at the offending character, which is left out of the scan.
+ */
+pub fn error_pointer(json: &str, error: &serde_json::Error) -> String {
+    let column = match error.classify() {
+        Category::Syntax => error.column().saturating_sub(1),
+        _ => error.column(),
+    };
+    pointer_at(json, error.line(), column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::Question;
+    use serde_json::{from_str, Value};
+
+    fn pointer<T: ::serde::de::DeserializeOwned>(json: &str) -> String {
+        let error = from_str::<T>(json).err().expect("Expected a parsing error");
+        error_pointer(json, &error)
+    }
+
+    #[test]
+    fn test_pointers_of_serde_errors() {
+        let wrong_type = r#"{
+            "question": "Why did the chicken cross the road",
+            "category": "Joke",
+            "choices": [
+                {"title": "To get to the other side", "correct": true},
+                {"title": "To commit suicide", "correct": "no"}
+            ]
+        }"#;
+        assert_eq!(pointer::<Question>(wrong_type), "/choices/1/correct");
+
+        let missing_field =
+            r#"{"question": "Why?", "category": "Joke", "choices": [{"title": "Because"}]}"#;
+        assert_eq!(pointer::<Question>(missing_field), "/choices/0");
+
+        let missing_top_level = r#"{"question": "Why?", "choices": []}"#;
+        assert_eq!(pointer::<Question>(missing_top_level), "");
+
+        let not_an_array = "{\n\"category\": \"Joke\",\n\"choices\": 42\n}";
+        assert_eq!(pointer::<Question>(not_an_array), "/choices");
+
+        for json in &[wrong_type, missing_field, missing_top_level, not_an_array] {
+            let document: Value = from_str(json).unwrap();
+            assert!(document.pointer(&pointer::<Question>(json)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_pointers_of_syntax_errors() {
+        assert_eq!(pointer::<Value>(r#"{"a": {"b/c": [1, 2, }"#), "/a/b~1c/2");
+        assert_eq!(pointer::<Value>(r#"{"a": "x\"}", "b": tru"#), "/b");
+        assert_eq!(pointer::<Value>(""), "");
+    }
+}
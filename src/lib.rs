@@ -0,0 +1,33 @@
+extern crate base64;
+extern crate brotli;
+extern crate chrono;
+extern crate flate2;
+extern crate http;
+extern crate lambda_runtime as lambda;
+extern crate log;
+extern crate postgres;
+extern crate ring;
+extern crate rustls;
+extern crate serde;
+extern crate serde_derive;
+extern crate serde_json;
+extern crate webpki_roots;
+
+pub mod apigateway;
+pub mod auth;
+pub mod authorizer;
+pub mod compression;
+pub mod connection;
+pub mod cors;
+pub mod events;
+pub mod handlers;
+pub mod in_memory;
+pub mod json_pointer;
+pub mod migrations;
+pub mod models;
+pub mod repositories;
+pub mod responses;
+pub mod router;
+pub mod sampling;
+pub mod tls;
+pub mod validation;
@@ -0,0 +1,52 @@
+extern crate log;
+extern crate quizzical;
+extern crate simple_logger;
+
+use quizzical::connection::connect;
+use quizzical::migrations::{current_version, migrate, MIGRATIONS};
+use std::env;
+use std::error::Error;
+
+const USAGE: &str = "usage: migrate [up|version]";
+
+/**
+ * Applies the embedded schema migrations to the database at `CONN_STRING`.
+ *
+ * `migrate up` (the default) applies pending migrations.
+ * `migrate version` prints the current schema version without changing the database.
+ */
+fn main() -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_level(log::Level::Info).unwrap();
+
+    let command = env::args().nth(1).unwrap_or_else(|| "up".into());
+    if command != "up" && command != "version" {
+        return Err(USAGE.into());
+    }
+
+    let conn_string = env::var("CONN_STRING").map_err(|_| "CONN_STRING required")?;
+    let conn = connect(&conn_string)?;
+
+    let latest = MIGRATIONS.last().map_or(0, |migration| migration.version);
+
+    match command.as_str() {
+        "up" => {
+            let applied = migrate(&conn)?;
+            println!(
+                "Applied {} migration(s). Schema version: {}.",
+                applied.len(),
+                current_version(&conn)?.unwrap_or(0)
+            );
+        }
+        "version" => {
+            let current = current_version(&conn)?.unwrap_or(0);
+            println!(
+                "Schema version: {} (latest: {}, pending: {}).",
+                current,
+                latest,
+                MIGRATIONS.iter().filter(|m| m.version > current).count()
+            );
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use log::info;
+use postgres::Connection;
+use repositories::RepositoryError;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/**
+ * Schema migrations, in the order they must be applied.
+ *
+ * Migrations are compiled into the crate from the `migrations` directory.
+ * A migration must never be edited once it has been applied to production; add a new one instead.
+ */
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_categories_questions_choices",
+        sql: include_str!("../migrations/0001_create_categories_questions_choices.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "cascade_category_renames",
+        sql: include_str!("../migrations/0002_cascade_category_renames.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "add_category_metadata",
+        sql: include_str!("../migrations/0003_add_category_metadata.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "index_questions_category",
+        sql: include_str!("../migrations/0004_index_questions_category.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "create_quiz_sessions",
+        sql: include_str!("../migrations/0005_create_quiz_sessions.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "create_scores",
+        sql: include_str!("../migrations/0006_create_scores.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "add_updated_at",
+        sql: include_str!("../migrations/0007_add_updated_at.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "add_question_difficulty",
+        sql: include_str!("../migrations/0008_add_question_difficulty.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "create_question_tags",
+        sql: include_str!("../migrations/0009_create_question_tags.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "protect_quiz_sessions",
+        sql: include_str!("../migrations/0010_protect_quiz_sessions.sql"),
+    },
+];
+
+const CREATE_SCHEMA_MIGRATIONS: &str = "CREATE TABLE IF NOT EXISTS schema_migrations(
+    version BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+)";
+
+/// Returns the version of the last applied migration, or `None` if no migration has been applied.
+pub fn current_version(conn: &Connection) -> Result<Option<i64>, RepositoryError> {
+    let exists: bool = conn
+        .query("SELECT to_regclass('schema_migrations') IS NOT NULL", &[])?
+        .get(0)
+        .get(0);
+    if !exists {
+        return Ok(None);
+    }
+
+    let rows = conn.query("SELECT MAX(version) FROM schema_migrations", &[])?;
+    Ok(match rows.is_empty() {
+        true => None,
+        false => rows.get(0).get(0),
+    })
+}
+
+/**
+ * Applies every migration newer than the current version, returning the applied migrations.
+ *
+ * All pending migrations are applied in a single transaction, holding a lock on
+ * `schema_migrations` so that concurrent runs can not apply the same migration twice.
+ */
+pub fn migrate(conn: &Connection) -> Result<Vec<&'static Migration>, RepositoryError> {
+    conn.execute(CREATE_SCHEMA_MIGRATIONS, &[])?;
+
+    let trans = conn.transaction()?;
+    trans.execute("LOCK TABLE schema_migrations IN EXCLUSIVE MODE", &[])?;
+
+    let rows = trans.query("SELECT MAX(version) FROM schema_migrations", &[])?;
+    let current: Option<i64> = match rows.is_empty() {
+        true => None,
+        false => rows.get(0).get(0),
+    };
+
+    let mut applied = vec![];
+    for migration in pending(current) {
+        info!(
+            "Applying migration {} '{}'.",
+            migration.version, migration.name
+        );
+
+        trans.batch_execute(migration.sql)?;
+        trans.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES ($1, $2)",
+            &[&migration.version, &migration.name],
+        )?;
+        applied.push(migration);
+    }
+
+    trans.commit()?;
+    Ok(applied)
+}
+
+fn pending(current: Option<i64>) -> Vec<&'static Migration> {
+    MIGRATIONS
+        .iter()
+        .filter(|migration| current.is_none_or(|version| migration.version > version))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_are_strictly_increasing() {
+        let versions: Vec<i64> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(versions.iter().all(|version| *version > 0));
+    }
+
+    #[test]
+    fn test_pending_skips_applied_migrations() {
+        assert_eq!(pending(None).len(), MIGRATIONS.len());
+
+        let latest = MIGRATIONS.last().unwrap().version;
+        assert!(pending(Some(latest)).is_empty());
+    }
+}
@@ -2,24 +2,121 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_json;
 
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
 use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug)]
+/// A violated constraint, identified by a stable machine readable `code`.
+#[derive(Debug, PartialEq)]
 pub enum ValidationError {
-    Constraint { pointer: String, message: String },
+    Constraint {
+        pointer: String,
+        code: String,
+        message: String,
+    },
 }
 
 impl std::fmt::Display for ValidationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let ValidationError::Constraint { pointer, message } = self;
-        write!(f, "ValidationError{{ {}: {} }}", pointer, message,)
+        let ValidationError::Constraint {
+            pointer,
+            code,
+            message,
+        } = self;
+        write!(
+            f,
+            "ValidationError{{ {} ({}): {} }}",
+            pointer, code, message
+        )
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/**
+ * Collects every violation found while validating a value.
+ *
+ * Validators keep checking after the first violation, so clients can fix all of them at once.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub struct ValidationErrors {
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> ValidationErrors {
+        ValidationErrors::default()
+    }
+
+    pub fn add<P: Into<String>, M: Into<String>>(&mut self, pointer: P, code: &str, message: M) {
+        self.errors.push(ValidationError::Constraint {
+            pointer: pointer.into(),
+            code: code.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Adds the violation unless `valid` holds.
+    pub fn check<P: Into<String>, M: Into<String>>(
+        &mut self,
+        valid: bool,
+        pointer: P,
+        code: &str,
+        message: M,
+    ) {
+        if !valid {
+            self.add(pointer, code, message);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// `Ok` if no violation was found.
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let errors: Vec<String> = self.errors.iter().map(|e| format!("{}", e)).collect();
+        write!(f, "[{}]", errors.join(", "))
+    }
+}
+
+/**
+ * A category of questions.
+ *
+ * Only `title` is required. The other fields are filled in when listing categories and are
+ * omitted from the JSON when unset, so clients that only read `title` are unaffected.
+ *
+ * `updated_at` is the last time the category or one of its questions was saved.
+ */
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Category {
     pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub question_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Category {
+    pub fn new<S: Into<String>>(title: S) -> Category {
+        Category {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,6 +124,19 @@ pub struct Categories {
     pub categories: Vec<Category>,
 }
 
+/// A category as seen by administrators, including inactive categories.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CategorySummary {
+    pub title: String,
+    pub active: bool,
+    pub question_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CategorySummaries {
+    pub categories: Vec<CategorySummary>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Choice {
     pub id: Option<i64>,
@@ -34,28 +144,499 @@ pub struct Choice {
     pub correct: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// How hard a question is. Levels are ordered, so they can be filtered by range.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// The level stored in the `questions.difficulty` column.
+    pub fn level(self) -> i16 {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => 2,
+            Difficulty::Hard => 3,
+        }
+    }
+
+    pub fn from_level(level: i16) -> Option<Difficulty> {
+        match level {
+            1 => Some(Difficulty::Easy),
+            2 => Some(Difficulty::Medium),
+            3 => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Difficulty, String> {
+        match value {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!(
+                "Invalid difficulty '{}'. Expected one of easy, medium, hard",
+                value
+            )),
+        }
+    }
+}
+
+/// An inclusive range of difficulties. The default range includes every difficulty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyRange {
+    pub min: Difficulty,
+    pub max: Difficulty,
+}
+
+impl Default for DifficultyRange {
+    fn default() -> DifficultyRange {
+        DifficultyRange {
+            min: Difficulty::Easy,
+            max: Difficulty::Hard,
+        }
+    }
+}
+
+impl DifficultyRange {
+    pub fn contains(&self, difficulty: Difficulty) -> bool {
+        self.min <= difficulty && difficulty <= self.max
+    }
+}
+
+/// Parses a single difficulty (`easy`) or a range (`easy..medium`, `medium..` or `..medium`).
+impl std::str::FromStr for DifficultyRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<DifficultyRange, String> {
+        let all = DifficultyRange::default();
+        let range = match value.find("..") {
+            Some(index) => {
+                let (min, max) = (&value[..index], &value[index + 2..]);
+                DifficultyRange {
+                    min: match min {
+                        "" => all.min,
+                        min => min.parse()?,
+                    },
+                    max: match max {
+                        "" => all.max,
+                        max => max.parse()?,
+                    },
+                }
+            }
+            None => {
+                let difficulty = value.parse()?;
+                DifficultyRange {
+                    min: difficulty,
+                    max: difficulty,
+                }
+            }
+        };
+        match range.min <= range.max {
+            true => Ok(range),
+            false => Err(format!("Empty difficulty range '{}'", value)),
+        }
+    }
+}
+
+/// Whether a question must have any or all of the tags of a `QuestionFilter`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+impl std::str::FromStr for TagMatch {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<TagMatch, String> {
+        match value {
+            "any" => Ok(TagMatch::Any),
+            "all" => Ok(TagMatch::All),
+            _ => Err(format!(
+                "Invalid match '{}'. Expected one of any, all",
+                value
+            )),
+        }
+    }
+}
+
+/// Tags are trimmed, lowercased and sorted, and blank or repeated tags are dropped.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+/**
+ * Selects questions, for `QuestionStore::count_questions` and `get_questions`.
+ *
+ * Questions are selected by category, by tags or both; tags cut across categories. A filter
+ * without tags matches questions regardless of their tags. Pages and their totals must be
+ * computed with the same filter.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QuestionFilter {
+    pub category: Option<String>,
+    pub difficulty: DifficultyRange,
+    /// Normalized tags (see `normalize_tags`).
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+}
+
+impl QuestionFilter {
+    pub fn category<S: Into<String>>(category: S) -> QuestionFilter {
+        QuestionFilter {
+            category: Some(category.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn tags(tags: &[String], tag_match: TagMatch) -> QuestionFilter {
+        QuestionFilter {
+            tags: normalize_tags(tags),
+            tag_match,
+            ..Default::default()
+        }
+    }
+
+    /// The number of the filter's tags a question must have.
+    pub fn required_tags(&self) -> usize {
+        match self.tag_match {
+            TagMatch::Any => self.tags.len().min(1),
+            TagMatch::All => self.tags.len(),
+        }
+    }
+
+    pub fn matches(&self, question: &Question) -> bool {
+        let matching_tags = self
+            .tags
+            .iter()
+            .filter(|tag| question.tags.contains(tag))
+            .count();
+        self.category
+            .as_ref()
+            .is_none_or(|category| &question.category == category)
+            && self.difficulty.contains(question.difficulty)
+            && matching_tags >= self.required_tags()
+    }
+}
+
+/**
+ * `updated_at` is set by the stores and ignored when saving or updating a question.
+ * Questions without a `difficulty` are of medium difficulty. The stores normalize `tags`
+ * (see `normalize_tags`).
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Question {
     pub id: Option<i64>,
     pub question: String,
     pub category: String,
     pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A choice as shown to players, without revealing whether it is correct.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerChoice {
+    pub id: Option<i64>,
+    pub title: String,
+}
+
+/**
+ * The player projection of a question.
+ *
+ * Public endpoints serve this projection so that the answers can not be read from the responses;
+ * answers are checked by the server instead (see `Question::check_answer`).
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerQuestion {
+    pub id: Option<i64>,
+    pub question: String,
+    pub category: String,
+    pub choices: Vec<PlayerChoice>,
+    pub difficulty: Difficulty,
+    pub tags: Vec<String>,
+}
+
+impl From<Question> for PlayerQuestion {
+    fn from(question: Question) -> Self {
+        PlayerQuestion {
+            id: question.id,
+            question: question.question,
+            category: question.category,
+            choices: question
+                .choices
+                .into_iter()
+                .map(|choice| PlayerChoice {
+                    id: choice.id,
+                    title: choice.title,
+                })
+                .collect(),
+            difficulty: question.difficulty,
+            tags: question.tags,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Answer {
+    pub choice_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AnswerResult {
+    pub question_id: i64,
+    pub choice_id: i64,
+    pub correct: bool,
+    pub correct_choice_id: Option<i64>,
 }
 
 impl Question {
-    pub fn validate(question: &Question) -> Result<(), ValidationError> {
-        if question
+    /// Trims the text, category and choice titles, so that they are saved as they were validated.
+    pub fn trimmed(mut self) -> Question {
+        self.question = self.question.trim().to_string();
+        self.category = self.category.trim().to_string();
+        for choice in self.choices.iter_mut() {
+            choice.title = choice.title.trim().to_string();
+        }
+        self
+    }
+
+    /// Returns `None` if `choice_id` is not one of the choices of this question.
+    pub fn check_answer(&self, choice_id: i64) -> Option<AnswerResult> {
+        let choice = self
+            .choices
+            .iter()
+            .find(|choice| choice.id == Some(choice_id))?;
+        let correct_choice_id = self
             .choices
             .iter()
-            .filter(|choice| choice.correct)
+            .find(|choice| choice.correct)
+            .and_then(|choice| choice.id);
+
+        Some(AnswerResult {
+            question_id: self.id.unwrap_or_default(),
+            choice_id,
+            correct: choice.correct,
+            correct_choice_id,
+        })
+    }
+}
+
+/**
+ * The answer given to one of the questions of a quiz session. Unanswered questions have no choice.
+ *
+ * Whether the answer is `correct` is recorded with it, so editing the question afterwards does not
+ * change the score of the session.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionAnswer {
+    pub question_id: i64,
+    pub choice_id: Option<i64>,
+    pub correct: Option<bool>,
+    pub answered_at: Option<DateTime<Utc>>,
+    pub time_taken_ms: Option<i64>,
+}
+
+/**
+ * A quiz played by a player, with the questions picked when the session was started.
+ *
+ * The session is completed once every question has been answered. Timings are measured by the
+ * server, from the start of the session or the previous answer, so they can be trusted for scoring.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: Option<i64>,
+    pub category: String,
+    /// Sessions without a player are not ranked in the leaderboards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub answers: Vec<SessionAnswer>,
+    /// The hash of the token that grants access to the session; the token itself is not stored.
+    #[serde(skip)]
+    pub token_hash: Option<String>,
+}
+
+impl Session {
+    pub fn new(category: &str, question_ids: &[i64], started_at: DateTime<Utc>) -> Session {
+        Session {
+            id: None,
+            category: category.to_string(),
+            player: None,
+            started_at,
+            completed_at: None,
+            answers: question_ids
+                .iter()
+                .map(|question_id| SessionAnswer {
+                    question_id: *question_id,
+                    choice_id: None,
+                    correct: None,
+                    answered_at: None,
+                    time_taken_ms: None,
+                })
+                .collect(),
+            token_hash: None,
+        }
+    }
+
+    pub fn answered(&self) -> usize {
+        self.answers
+            .iter()
+            .filter(|answer| answer.answered_at.is_some())
+            .count()
+    }
+
+    pub fn score(&self) -> usize {
+        self.answers
+            .iter()
+            .filter(|answer| answer.correct == Some(true))
             .count()
-            > 1
-        {
-            return Err(ValidationError::Constraint {
-                pointer: "/data/attribute/choices".into(),
-                message: "Only one correct choice allowed".into(),
-            });
-        }
-        Ok(())
+    }
+
+    /// Milliseconds elapsed between the last answer (or the start) and `at`.
+    pub fn time_since_last_answer(&self, at: DateTime<Utc>) -> i64 {
+        let last = self
+            .answers
+            .iter()
+            .filter_map(|answer| answer.answered_at)
+            .max()
+            .unwrap_or(self.started_at);
+        (at - last).num_milliseconds().max(0)
+    }
+}
+
+/// The final score of a completed session, as recorded for the leaderboards.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Score {
+    pub session_id: i64,
+    pub player: String,
+    pub category: String,
+    pub score: i64,
+    pub total: i64,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl Score {
+    /// Returns `None` unless the session is completed and was played by a named player.
+    pub fn from_session(session: &Session) -> Option<Score> {
+        Some(Score {
+            session_id: session.id?,
+            player: session.player.clone()?,
+            category: session.category.clone(),
+            score: session.score() as i64,
+            total: session.answers.len() as i64,
+            completed_at: session.completed_at?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub rank: i64,
+    pub player: String,
+    pub score: i64,
+    pub total: i64,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// The period covered by a leaderboard. Days and weeks (starting on Monday) are in UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Daily,
+    Weekly,
+    All,
+}
+
+impl Period {
+    /// The start of the period containing `now`, or `None` for all time.
+    pub fn since(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = Utc.ymd(now.year(), now.month(), now.day()).and_hms(0, 0, 0);
+        match self {
+            Period::Daily => Some(today),
+            Period::Weekly => {
+                Some(today - Duration::days(i64::from(now.weekday().num_days_from_monday())))
+            }
+            Period::All => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Period {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Period, String> {
+        match value {
+            "daily" => Ok(Period::Daily),
+            "weekly" => Ok(Period::Weekly),
+            "all" => Ok(Period::All),
+            _ => Err(format!(
+                "Invalid period '{}'. Expected one of daily, weekly, all",
+                value
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_since() {
+        // A Wednesday.
+        let now = Utc.ymd(2019, 3, 13).and_hms(15, 30, 0);
+
+        assert_eq!(
+            Period::Daily.since(now),
+            Some(Utc.ymd(2019, 3, 13).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            Period::Weekly.since(now),
+            Some(Utc.ymd(2019, 3, 11).and_hms(0, 0, 0))
+        );
+        assert_eq!(Period::All.since(now), None);
+    }
+
+    #[test]
+    fn test_parse_difficulty_range() {
+        let range = |min, max| DifficultyRange { min, max };
+        use self::Difficulty::*;
+
+        assert_eq!("easy".parse(), Ok(range(Easy, Easy)));
+        assert_eq!("easy..medium".parse(), Ok(range(Easy, Medium)));
+        assert_eq!("medium..".parse(), Ok(range(Medium, Hard)));
+        assert_eq!("..medium".parse(), Ok(range(Easy, Medium)));
+        assert!("hard..easy".parse::<DifficultyRange>().is_err());
+        assert!("expert".parse::<DifficultyRange>().is_err());
+        assert!(range(Easy, Medium).contains(Medium));
+        assert!(!range(Easy, Medium).contains(Hard));
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags: Vec<String> = vec![" Space ".into(), "space".into(), "".into(), "NASA".into()];
+        assert_eq!(normalize_tags(&tags), vec!["nasa", "space"]);
     }
 }
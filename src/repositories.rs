@@ -1,13 +1,18 @@
-use apigateway::APIErrorResponse;
-use http::StatusCode;
+use chrono::{DateTime, Utc};
+use connection::ConnectionManager;
 use log::{error, info};
-use models::{Categories, Category, Choice, Question};
+use models::{
+    normalize_tags, Categories, Category, CategorySummaries, CategorySummary, Choice, Difficulty,
+    LeaderboardEntry, Question, QuestionFilter, Score, Session, SessionAnswer,
+};
 use postgres::rows::Rows;
+use postgres::transaction::Transaction;
 use postgres::types::ToSql;
 use postgres::Connection;
+use sampling::sample;
 use std::collections::HashMap;
 use std::fmt;
-use std::sync::Arc;
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub enum SaveCategoryStatus {
@@ -16,6 +21,7 @@ pub enum SaveCategoryStatus {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum RepositoryError {
     ConnectionError(String),
     DatabaseError(String, String),
@@ -38,6 +44,8 @@ impl std::fmt::Display for RepositoryError {
     }
 }
 
+impl std::error::Error for RepositoryError {}
+
 impl std::convert::From<postgres::Error> for RepositoryError {
     fn from(error: postgres::Error) -> Self {
         if let Some(connection_error) = error.as_connection() {
@@ -59,314 +67,1092 @@ impl std::convert::From<postgres::Error> for RepositoryError {
             return RepositoryError::IOError(format!("{}", io_error));
         }
 
-        return RepositoryError::UnknownError(Some(format!("{}", error)));
+        RepositoryError::UnknownError(Some(format!("{}", error)))
     }
 }
 
-pub struct CategoriesRepository {
-    pub conn: Arc<Connection>,
+/// The error of deleting a category that still has questions or quiz sessions, without cascade.
+pub fn category_in_use(name: &str) -> RepositoryError {
+    RepositoryError::DatabaseError(
+        "23503".into(),
+        format!("Category '{}' still has questions or quiz sessions", name),
+    )
 }
 
-impl CategoriesRepository {
-    pub fn save_category(
-        &self,
-        category: &Category,
-    ) -> Result<SaveCategoryStatus, RepositoryError> {
+/**
+ * Storage for categories.
+ *
+ * Handlers only depend on this trait (through `Config`), so that they can be run against
+ * Postgres (`CategoriesRepository`) in production and against `InMemoryStore` in unit tests.
+ */
+pub trait CategoryStore {
+    fn save_category(&self, category: &Category) -> Result<SaveCategoryStatus, RepositoryError> {
         self.save_category_and_set_active(category, None)
     }
 
-    pub fn save_category_and_set_active(
+    /**
+     * Inserts the category if it does not exist.
+     *
+     * If `active` is `None`, an existing category is left untouched and `Exists` is returned.
+     * If `active` is set, the active flag of an existing category is overwritten.
+     */
+    fn save_category_and_set_active(
+        &self,
+        category: &Category,
+        active: Option<bool>,
+    ) -> Result<SaveCategoryStatus, RepositoryError>;
+
+    /// Lists active categories only, with their metadata and question counts.
+    fn list_categories(&self) -> Result<Categories, RepositoryError>;
+
+    /// Returns `true` only if the active flag of the category was changed.
+    fn set_category_active(&self, name: &str, active: bool) -> Result<bool, RepositoryError>;
+
+    /// Lists all categories, including inactive ones, with the number of questions in each.
+    fn list_category_summaries(&self) -> Result<CategorySummaries, RepositoryError>;
+
+    /**
+     * Renames the category, moving its questions to the new name.
+     *
+     * Returns `false` if the category does not exist. Renaming to the name of another category
+     * fails with a unique violation (`23505`).
+     */
+    fn rename_category(&self, name: &str, new_name: &str) -> Result<bool, RepositoryError>;
+
+    /**
+     * Deletes the category. Returns `false` if it did not exist.
+     *
+     * Without `cascade`, fails with a foreign key violation (`23503`) while questions or quiz
+     * sessions belong to the category. With `cascade`, its questions, quiz sessions and their
+     * scores are deleted as well; this still fails with `23503` if one of its questions was moved
+     * from a category whose sessions still include it.
+     */
+    fn delete_category(&self, name: &str, cascade: bool) -> Result<bool, RepositoryError>;
+}
+
+/**
+ * Storage for questions and their choices.
+ *
+ * Questions belonging to inactive categories are not counted or returned.
+ */
+pub trait QuestionStore {
+    /// Saves the question, its choices and tags, returning a copy with the generated ids set and the tags normalized.
+    fn save_question(&self, question: &Question) -> Result<Question, RepositoryError>;
+
+    fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, RepositoryError>;
+
+    /// `page` is 1-based; page 0 is treated as the first page. Questions are returned in the
+    /// order of their ids, so that consecutive pages neither repeat nor skip questions.
+    fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        page: i64,
+        size: i64,
+    ) -> Result<Vec<Question>, RepositoryError>;
+
+    /**
+     * Returns up to `count` questions of the category picked at random.
+     *
+     * Questions are sampled from the ids of the category ordered by id, so the same seed
+     * returns the same questions as long as the category is unchanged.
+     */
+    fn sample_questions(
+        &self,
+        category: &str,
+        count: usize,
+        seed: u64,
+    ) -> Result<Vec<Question>, RepositoryError>;
+
+    /// Returns the question regardless of whether its category is active.
+    fn get_question(&self, id: i64) -> Result<Option<Question>, RepositoryError>;
+
+    /// Returns the question only if its category is active, for the public routes.
+    fn get_active_question(&self, id: i64) -> Result<Option<Question>, RepositoryError>;
+
+    /**
+     * Replaces the text, category, choices and tags of a question.
+     *
+     * The existing choices are deleted, so the returned choices have new ids.
+     * Returns `None` if the question does not exist.
+     */
+    fn update_question(
+        &self,
+        id: i64,
+        question: &Question,
+    ) -> Result<Option<Question>, RepositoryError>;
+
+    /**
+     * Returns `true` if the question existed and was deleted, along with its choices.
+     *
+     * Questions of quiz sessions are kept, so the delete fails with a foreign key violation
+     * (`23503`).
+     */
+    fn delete_question(&self, id: i64) -> Result<bool, RepositoryError>;
+}
+
+/**
+ * Storage for quiz sessions and the answers given in them.
+ */
+pub trait SessionStore {
+    /// Saves the session and its unanswered questions, returning a copy with the id set.
+    fn create_session(&self, session: &Session) -> Result<Session, RepositoryError>;
+
+    fn get_session(&self, id: i64) -> Result<Option<Session>, RepositoryError>;
+
+    /**
+     * Records the answer to one of the questions of the session. If it was the last unanswered
+     * question, the session is completed at `answer.answered_at`.
+     *
+     * Returns `false` if the question is not part of the session or was already answered.
+     */
+    fn record_answer(
+        &self,
+        session_id: i64,
+        answer: &SessionAnswer,
+    ) -> Result<bool, RepositoryError>;
+}
+
+/**
+ * Storage for the scores of completed sessions, ranked per category.
+ *
+ * Only the best score of each player counts; ties are broken by the earliest completion.
+ */
+pub trait LeaderboardStore {
+    /// Recording the score of the same session twice has no effect.
+    fn record_score(&self, score: &Score) -> Result<(), RepositoryError>;
+
+    /// Counts the players with a score in the category completed since `since`.
+    fn count_players(
+        &self,
+        category: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<i64, RepositoryError>;
+
+    /// `page` is 1-based; page 0 is treated as the first page.
+    fn get_leaderboard(
+        &self,
+        category: &str,
+        since: Option<DateTime<Utc>>,
+        page: i64,
+        size: i64,
+    ) -> Result<Vec<LeaderboardEntry>, RepositoryError>;
+}
+
+pub struct CategoriesRepository {
+    pub connections: Rc<ConnectionManager>,
+}
+
+impl CategoryStore for CategoriesRepository {
+    fn save_category_and_set_active(
         &self,
         category: &Category,
         active: Option<bool>,
     ) -> Result<SaveCategoryStatus, RepositoryError> {
-        info!("save_category(category: '{:?}').", category);
-
-        let (field_names, value_placeholders, on_conflict, values) = match active {
-            Some(x) => (
-                "(name,active)",
-                "($1,$2)",
-                "ON CONFLICT(name) DO UPDATE SET active=$2",
-                vec![&category.title as &ToSql, &active as &ToSql],
-            ),
-            None => (
-                "(name)",
-                "($1)",
-                "ON CONFLICT DO NOTHING",
-                vec![&category.title as &ToSql],
-            ),
-        };
+        self.connections.with_connection(|conn| {
+            info!("save_category(category: '{:?}').", category);
 
-        let query_string = &format!(
-            "INSERT INTO categories {} VALUES {} {};",
-            field_names, value_placeholders, on_conflict
-        );
+            let (field_names, value_placeholders, on_conflict, values) = match active {
+                Some(_) => (
+                    "(name,description,icon_url,active)",
+                    "($1,$2,$3,$4)",
+                    "ON CONFLICT(name) DO UPDATE SET active=$4, updated_at=now()",
+                    vec![
+                        &category.title as &dyn ToSql,
+                        &category.description as &dyn ToSql,
+                        &category.icon_url as &dyn ToSql,
+                        &active as &dyn ToSql,
+                    ],
+                ),
+                None => (
+                    "(name,description,icon_url)",
+                    "($1,$2,$3)",
+                    "ON CONFLICT DO NOTHING",
+                    vec![
+                        &category.title as &dyn ToSql,
+                        &category.description as &dyn ToSql,
+                        &category.icon_url as &dyn ToSql,
+                    ],
+                ),
+            };
 
-        let affected_rows = &self.conn.execute(query_string, values.as_slice())?;
+            let query_string = &format!(
+                "INSERT INTO categories {} VALUES {} {};",
+                field_names, value_placeholders, on_conflict
+            );
 
-        info!(
-            "Inserting category suceeded with affected rows '{:?}'.",
-            affected_rows
-        );
+            let affected_rows = conn.execute(query_string, values.as_slice())?;
 
-        Ok(match affected_rows {
-            x if x > &0u64 => SaveCategoryStatus::Created,
-            _ => SaveCategoryStatus::Exists,
+            info!(
+                "Inserting category suceeded with affected rows '{:?}'.",
+                affected_rows
+            );
+
+            Ok(match affected_rows {
+                x if x > 0u64 => SaveCategoryStatus::Created,
+                _ => SaveCategoryStatus::Exists,
+            })
         })
     }
 
-    pub fn list_categories(&self) -> Result<Categories, RepositoryError> {
-        let rows = &self
-            .conn
-            .query("SELECT name FROM categories WHERE active = true", &[])?;
+    fn list_categories(&self) -> Result<Categories, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let rows = &conn.query(
+                "SELECT c.name, COUNT(q.id), c.description, c.icon_url, c.created_at, \
+                 GREATEST(c.updated_at, MAX(q.updated_at)) \
+                 FROM categories c LEFT JOIN questions q ON q.category = c.name \
+                 WHERE c.active = true \
+                 GROUP BY c.name",
+                &[],
+            )?;
 
-        let mut categories: Vec<Category> = Vec::with_capacity(rows.len());
+            let mut categories: Vec<Category> = Vec::with_capacity(rows.len());
 
-        for row in rows {
-            categories.push(Category { title: row.get(0) });
-        }
+            for row in rows {
+                categories.push(Category {
+                    title: row.get(0),
+                    question_count: row.get(1),
+                    description: row.get(2),
+                    icon_url: row.get(3),
+                    created_at: row.get(4),
+                    updated_at: row.get(5),
+                });
+            }
 
-        Ok(Categories {
-            categories: categories,
+            Ok(Categories { categories })
         })
     }
 
-    pub fn set_category_active(&self, name: &str, active: bool) -> Result<bool, RepositoryError> {
-        let affected_rows = self.conn.execute(
-            "UPDATE categories SET active = $1 WHERE name = $2 AND active=$3",
-            &[&active, &name, &(!active)],
-        )?;
+    fn set_category_active(&self, name: &str, active: bool) -> Result<bool, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            let affected_rows = conn.execute(
+                "UPDATE categories SET active = $1, updated_at = now() WHERE name = $2 AND active=$3",
+                &[&active, &name, &(!active)],
+            )?;
 
-        Ok(affected_rows > 0u64)
+            Ok(affected_rows > 0u64)
+        })
+    }
+
+    fn list_category_summaries(&self) -> Result<CategorySummaries, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let rows = &conn.query(
+                "SELECT c.name, c.active, COUNT(q.id) FROM categories c \
+                 LEFT JOIN questions q ON q.category = c.name \
+                 GROUP BY c.name, c.active ORDER BY c.name",
+                &[],
+            )?;
+
+            let mut categories: Vec<CategorySummary> = Vec::with_capacity(rows.len());
+
+            for row in rows {
+                categories.push(CategorySummary {
+                    title: row.get(0),
+                    active: row.get(1),
+                    question_count: row.get(2),
+                });
+            }
+
+            Ok(CategorySummaries { categories })
+        })
+    }
+
+    fn rename_category(&self, name: &str, new_name: &str) -> Result<bool, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            info!(
+                "rename_category(name: '{}', new_name: '{}').",
+                name, new_name
+            );
+
+            // questions.category is updated by the ON UPDATE CASCADE foreign key.
+            let affected_rows = conn
+                .execute(
+                    "UPDATE categories SET name = $1, updated_at = now() WHERE name = $2",
+                    &[&new_name, &name],
+                )
+                .inspect_err(|e| error!("Renaming category '{}' failed: {}", name, e))?;
+
+            Ok(affected_rows > 0u64)
+        })
+    }
+
+    fn delete_category(&self, name: &str, cascade: bool) -> Result<bool, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            let trans = conn.transaction()?;
+
+            if !cascade {
+                let in_use: bool = trans
+                    .query(
+                        "SELECT EXISTS (SELECT 1 FROM questions WHERE category = $1) OR EXISTS (SELECT 1 FROM quiz_sessions WHERE category = $1)",
+                        &[&name],
+                    )
+                    .inspect_err(|e| error!("Checking use of category '{}' failed: {}", name, e))?
+                    .get(0)
+                    .get(0);
+                if in_use {
+                    trans.set_rollback();
+                    return Err(category_in_use(name));
+                }
+            }
+
+            // The sessions go first: their answers keep the questions from being deleted.
+            trans
+                .execute("DELETE FROM quiz_sessions WHERE category = $1", &[&name])
+                .inspect_err(|e| {
+                    error!("Deleting sessions of category '{}' failed: {}", name, e);
+                    trans.set_rollback();
+                })?;
+            let affected_rows = trans
+                .execute("DELETE FROM categories WHERE name = $1", &[&name])
+                .inspect_err(|e| {
+                    error!("Deleting category '{}' failed: {}", name, e);
+                    trans.set_rollback();
+                })?;
+
+            trans.commit()?;
+            Ok(affected_rows > 0u64)
+        })
     }
 }
 
 pub struct QuestionsRepository {
-    pub conn: Arc<Connection>,
+    pub connections: Rc<ConnectionManager>,
 }
 
-impl QuestionsRepository {
-    pub fn save_question(&self, question: &Question) -> Result<Question, RepositoryError> {
-        info!("save_question(question: '{:?}').", question);
+impl QuestionStore for QuestionsRepository {
+    fn save_question(&self, question: &Question) -> Result<Question, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            info!("save_question(question: '{:?}').", question);
 
-        let trans = self.conn.transaction()?;
+            let trans = conn.transaction()?;
 
-        info!("Inserting question '{:?}' into database.", question);
+            info!("Inserting question '{:?}' into database.", question);
 
-        let id_rows = &trans
-            .query(
-                "INSERT INTO questions (text, category) VALUES ($1, $2) RETURNING id",
-                &[&question.question, &question.category],
-            )
-            .or_else(|e| {
-                error!(
-                    "Insert question failed for question: '{:?}', with reason: '{:?}'.",
-                    question, e
-                );
-                //rollback will happen when transaction is dropped (i.e. Destructor)
-                trans.set_rollback();
-                Err(e)
-            })?;
-
-        info!(
-            "Insert question succeeded for question: '{:?}', with updated rows: '{:?}'.",
-            question, id_rows,
-        );
+            let id_rows = &trans
+                .query(
+                    "INSERT INTO questions (text, category, difficulty) VALUES ($1, $2, $3) RETURNING id, updated_at",
+                    &[&question.question, &question.category, &question.difficulty.level()],
+                )
+                .inspect_err(|e| {
+                    error!(
+                        "Insert question failed for question: '{:?}', with reason: '{:?}'.",
+                        question, e
+                    );
+                    //rollback will happen when transaction is dropped (i.e. Destructor)
+                    trans.set_rollback();
+                })?;
 
-        let question_id: i64 = id_rows
-            .iter()
-            .next()
-            .and_then(|row| row.get(0))
-            .ok_or(RepositoryError::UnknownError(Some(
-                "Failed to get question id".into(),
-            )))
-            .map_err(|e| {
-                error!(
-                    "Insert question succeeded but no id received for question: '{:?}'.",
-                    question
-                );
-                trans.set_rollback();
-                e
-            })?;
-
-        //Since we don't know how many choices a question has, we need to build a query string for bulk insert manually.
-
-        //value_placeholders refers to the `($1, $2)` part of the query.
-        let mut value_placeholders: Vec<String> = vec![];
-        //total is the number of fields to be inserted per choice multiplied by the number of choices
-        let num_fields = 3;
-        let total = num_fields * question.choices.len();
-
-        for i in (0..total).step_by(num_fields) {
-            value_placeholders.push(format!("(${}, ${}, ${})", i + 1, i + 2, i + 3))
-        }
+            info!(
+                "Insert question succeeded for question: '{:?}', with updated rows: '{:?}'.",
+                question, id_rows,
+            );
 
-        //join all the value placeholders i.e. ($1,$2), ($3,$4)
-        let joined_value_placeholders = value_placeholders.join(",");
+            let (question_id, updated_at): (i64, DateTime<Utc>) = id_rows
+                .iter()
+                .next()
+                .map(|row| (row.get(0), row.get(1)))
+                .ok_or(RepositoryError::UnknownError(Some(
+                    "Failed to get question id".into(),
+                )))
+                .inspect_err(|_| {
+                    error!(
+                        "Insert question succeeded but no id received for question: '{:?}'.",
+                        question
+                    );
+                    trans.set_rollback();
+                })?;
 
-        let query_string = &format!(
-            "INSERT INTO choices (question_id, text, correct) VALUES {} RETURNING id",
-            joined_value_placeholders
-        );
+            let choices_with_ids = insert_choices(&trans, question_id, &question.choices)?;
+            let tags = insert_tags(&trans, question_id, &question.tags)?;
 
-        let mut values: Vec<&ToSql> = vec![];
-        for choice in question.choices.iter() {
-            values.push(&question_id);
-            values.push(&choice.title);
-            values.push(&choice.correct);
-        }
+            trans.set_commit();
 
-        info!(
-            "Will insert choices for question id '{}' using query '{}' and values '{:?}'.",
-            question_id, query_string, values
-        );
+            trans
+                .finish()
+                .map_err(|e| {
+                    error!(
+                        "Finishing insert question failed for question_id '{}' with reason '{}'.",
+                        question_id, e
+                    );
+                    e.into()
+                })
+                .and(Ok(Question {
+                    id: Some(question_id),
+                    question: question.question.clone(),
+                    category: question.category.clone(),
+                    choices: choices_with_ids,
+                    difficulty: question.difficulty,
+                    tags,
+                    updated_at: Some(updated_at),
+                }))
+        })
+    }
 
-        let rows: Rows = trans.query(query_string, values.as_slice()).or_else(|e| {
-            error!(
-                "Bulk insert choices failed for question_id: '{}', reason: {}.",
-                question_id, e
-            );
-            //rollback will happen when transaction is dropped (i.e. Destructor)
-            trans.set_rollback();
-            Err(e)
-        })?;;
-
-        // Create a new vector of choices, with the id field set.
-        let ids: Vec<i64> = rows.iter().map(|row| row.get(0)).collect();
-        let choices_with_ids = question
-            .choices
-            .iter()
-            .zip(ids.iter())
-            .map(|choice_id_tuple| {
-                let choice = choice_id_tuple.0;
-                let id = choice_id_tuple.1;
-                Choice {
-                    id: Some(*id),
-                    title: choice.title.clone(),
-                    correct: choice.correct,
-                }
-            })
-            .collect();
-
-        trans.set_commit();
-
-        trans
-            .finish()
-            .map_err(|e| {
-                error!(
-                    "Finishing insert question failed for question_id '{}' with reason '{}'.",
-                    question_id, e
-                );
-                e.into()
+    fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let count_rows = &conn
+                .query(
+                    &format!(
+                        "SELECT COUNT(q.id) FROM questions q INNER JOIN categories c ON c.name = q.category WHERE {}",
+                        QUESTION_FILTER_CONDITIONS
+                    ),
+                    &[
+                        &filter.category,
+                        &filter.difficulty.min.level(),
+                        &filter.difficulty.max.level(),
+                        &filter.tags,
+                        &(filter.required_tags() as i64),
+                    ],
+                )
+                .map_err(|e| {
+                    error!("Error counting questions for '{:?}': {}", filter, e);
+                    e
+                })?;
+
+            let count: i64 = match count_rows.is_empty() {
+                true => 0i64,
+                false => count_rows.get(0).get(0),
+            };
+
+            Ok(count)
+        })
+    }
+
+    fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        page: i64,
+        size: i64,
+    ) -> Result<Vec<Question>, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let offset = match page {
+                0 => 0i64,
+                _ => (page - 1i64) * size,
+            };
+
+            let question_rows = &conn
+                .query(
+                    &format!(
+                        "SELECT q.id,q.text,q.updated_at,q.difficulty,q.category FROM questions q INNER JOIN categories c ON c.name = q.category WHERE {} ORDER BY q.id LIMIT $6 OFFSET $7",
+                        QUESTION_FILTER_CONDITIONS
+                    ),
+                    &[
+                        &filter.category,
+                        &filter.difficulty.min.level(),
+                        &filter.difficulty.max.level(),
+                        &filter.tags,
+                        &(filter.required_tags() as i64),
+                        &size,
+                        &offset,
+                    ],
+                )
+                .map_err(|e| {
+                    error!("Error loading questions for '{:?}': {}", filter, e);
+                    e
+                })?;
+
+            if question_rows.is_empty() {
+                return Ok(vec![]);
+            }
+
+            let mut question_ids: Vec<i64> = vec![];
+            for question_row in question_rows {
+                let id: i64 = question_row.get(0);
+                question_ids.push(id);
+            }
+
+            let choices_map = load_choices(conn, &question_ids)?;
+            let mut tags_map = load_tags(conn, &question_ids)?;
+
+            let mut questions: Vec<Question> = Vec::with_capacity(question_rows.len());
+            for question_row in question_rows {
+                let id: i64 = question_row.get(0);
+                let text: String = question_row.get(1);
+                let choices: Vec<Choice> = choices_map.get(&id).unwrap_or(&vec![]).to_vec();
+
+                questions.push(Question {
+                    id: Some(id),
+                    question: text,
+                    category: question_row.get(4),
+                    choices,
+                    difficulty: difficulty(question_row.get(3)),
+                    tags: tags_map.remove(&id).unwrap_or_default(),
+                    updated_at: question_row.get(2),
+                });
+            }
+
+            Ok(questions)
+        })
+    }
+
+    fn sample_questions(
+        &self,
+        category: &str,
+        count: usize,
+        seed: u64,
+    ) -> Result<Vec<Question>, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            // Only the ids are read (from the category index) and sampled here, which is cheaper
+            // than sorting every row of the category with ORDER BY random().
+            let id_rows = &conn
+                .query(
+                    "SELECT q.id FROM questions q INNER JOIN categories c ON c.name = q.category WHERE c.name = $1 AND c.active = TRUE ORDER BY q.id",
+                    &[&category],
+                )
+                .inspect_err(|e| {
+                    error!("Error loading question ids for category '{}': {}", category, e)
+                })?;
+
+            let ids: Vec<i64> = id_rows.iter().map(|row| row.get(0)).collect();
+            let sampled_ids = sample(&ids, count, seed);
+
+            load_questions(conn, &sampled_ids)
+        })
+    }
+
+    fn get_question(&self, id: i64) -> Result<Option<Question>, RepositoryError> {
+        self.connections
+            .with_read_connection(|conn| Ok(load_questions(conn, &[id])?.pop()))
+    }
+
+    fn get_active_question(&self, id: i64) -> Result<Option<Question>, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let id_rows = &conn
+                .query(
+                    "SELECT q.id FROM questions q INNER JOIN categories c ON c.name = q.category WHERE q.id = $1 AND c.active = TRUE",
+                    &[&id],
+                )
+                .inspect_err(|e| error!("Error loading active question '{}': {}", id, e))?;
+
+            let ids: Vec<i64> = id_rows.iter().map(|row| row.get(0)).collect();
+            Ok(load_questions(conn, &ids)?.pop())
+        })
+    }
+
+    fn update_question(
+        &self,
+        id: i64,
+        question: &Question,
+    ) -> Result<Option<Question>, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            info!("update_question(id: '{}', question: '{:?}').", id, question);
+
+            let trans = conn.transaction()?;
+
+            let updated_rows = trans
+                .query(
+                    "UPDATE questions SET text = $1, category = $2, difficulty = $3, updated_at = now() WHERE id = $4 RETURNING updated_at",
+                    &[&question.question, &question.category, &question.difficulty.level(), &id],
+                )
+                .inspect_err(|e| {
+                    error!("Update question '{}' failed with reason: '{:?}'.", id, e);
+                    trans.set_rollback();
+                })?;
+
+            let updated_at: DateTime<Utc> = match updated_rows.iter().next() {
+                Some(row) => row.get(0),
+                None => return Ok(None),
+            };
+
+            trans
+                .execute("DELETE FROM choices WHERE question_id = $1", &[&id])
+                .inspect_err(|e| {
+                    error!(
+                        "Deleting choices of question '{}' failed with reason: '{:?}'.",
+                        id, e
+                    );
+                    trans.set_rollback();
+                })?;
+
+            trans
+                .execute("DELETE FROM question_tags WHERE question_id = $1", &[&id])
+                .inspect_err(|e| {
+                    error!(
+                        "Deleting tags of question '{}' failed with reason: '{:?}'.",
+                        id, e
+                    );
+                    trans.set_rollback();
+                })?;
+
+            let choices_with_ids = insert_choices(&trans, id, &question.choices)?;
+            let tags = insert_tags(&trans, id, &question.tags)?;
+
+            trans.set_commit();
+
+            trans
+                .finish()
+                .map_err(|e| {
+                    error!(
+                        "Finishing update question failed for question_id '{}' with reason '{}'.",
+                        id, e
+                    );
+                    e.into()
+                })
+                .and(Ok(Some(Question {
+                    id: Some(id),
+                    question: question.question.clone(),
+                    category: question.category.clone(),
+                    choices: choices_with_ids,
+                    difficulty: question.difficulty,
+                    tags,
+                    updated_at: Some(updated_at),
+                })))
+        })
+    }
+
+    fn delete_question(&self, id: i64) -> Result<bool, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            let affected_rows = conn.execute("DELETE FROM questions WHERE id = $1", &[&id])?;
+
+            Ok(affected_rows > 0u64)
+        })
+    }
+}
+
+pub struct SessionsRepository {
+    pub connections: Rc<ConnectionManager>,
+}
+
+impl SessionStore for SessionsRepository {
+    fn create_session(&self, session: &Session) -> Result<Session, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            info!("create_session(session: '{:?}').", session);
+
+            let trans = conn.transaction()?;
+
+            let id_rows = &trans
+                .query(
+                    "INSERT INTO quiz_sessions (category, player, started_at, token_hash) VALUES ($1, $2, $3, $4) RETURNING id",
+                    &[&session.category, &session.player, &session.started_at, &session.token_hash],
+                )
+                .inspect_err(|e| {
+                    error!("Insert session failed for '{:?}': {}", session, e);
+                    trans.set_rollback();
+                })?;
+            let session_id: i64 = id_rows.get(0).get(0);
+
+            let question_ids: Vec<i64> = session
+                .answers
+                .iter()
+                .map(|answer| answer.question_id)
+                .collect();
+            trans
+                .execute(
+                    "INSERT INTO quiz_session_answers (session_id, position, question_id) \
+                     SELECT $1, t.position::INT, t.question_id \
+                     FROM unnest($2::BIGINT[]) WITH ORDINALITY AS t(question_id, position)",
+                    &[&session_id, &question_ids],
+                )
+                .inspect_err(|e| {
+                    error!("Insert questions of session '{}' failed: {}", session_id, e);
+                    trans.set_rollback();
+                })?;
+
+            trans.commit()?;
+
+            Ok(Session {
+                id: Some(session_id),
+                ..session.clone()
             })
-            .and(Ok(Question {
-                id: Some(question_id),
-                question: question.question.clone(),
-                category: question.category.clone(),
-                choices: choices_with_ids,
+        })
+    }
+
+    fn get_session(&self, id: i64) -> Result<Option<Session>, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let session_rows = &conn.query(
+                "SELECT category, player, started_at, completed_at, token_hash FROM quiz_sessions WHERE id = $1",
+                &[&id],
+            )?;
+
+            if session_rows.is_empty() {
+                return Ok(None);
+            }
+            let session_row = session_rows.get(0);
+
+            let answer_rows = &conn.query(
+                "SELECT question_id, choice_id, correct, answered_at, time_taken_ms \
+                 FROM quiz_session_answers WHERE session_id = $1 ORDER BY position",
+                &[&id],
+            )?;
+
+            Ok(Some(Session {
+                id: Some(id),
+                category: session_row.get(0),
+                player: session_row.get(1),
+                started_at: session_row.get(2),
+                completed_at: session_row.get(3),
+                answers: answer_rows
+                    .iter()
+                    .map(|row| SessionAnswer {
+                        question_id: row.get(0),
+                        choice_id: row.get(1),
+                        correct: row.get(2),
+                        answered_at: row.get(3),
+                        time_taken_ms: row.get(4),
+                    })
+                    .collect(),
+                token_hash: session_row.get(4),
             }))
+        })
     }
 
-    pub fn count_questions(&self, category: &str) -> Result<i64, RepositoryError> {
-        let count_rows = &self
-            .conn
-            .query(
-                "SELECT COUNT(q.id) FROM questions q INNER JOIN categories c ON c.name = q.category WHERE c.name = $1 AND c.active = TRUE",
-                &[&category],
-            )
-            .map_err(|e| {
-                error!(
-                    "Error counting questions for category '{}': {}",
-                    category, e
-                );
-                e
-            })?;
-
-        let count: i64 = match count_rows.is_empty() {
-            true => 0i64,
-            false => count_rows.get(0).get(0),
-        };
+    fn record_answer(
+        &self,
+        session_id: i64,
+        answer: &SessionAnswer,
+    ) -> Result<bool, RepositoryError> {
+        self.connections.with_connection(|conn| {
+            info!(
+                "record_answer(session_id: '{}', answer: '{:?}').",
+                session_id, answer
+            );
+
+            let trans = conn.transaction()?;
+
+            let updated_rows = trans
+                .execute(
+                    "UPDATE quiz_session_answers \
+                     SET choice_id = $3, correct = $4, answered_at = $5, time_taken_ms = $6 \
+                     WHERE session_id = $1 AND question_id = $2 AND answered_at IS NULL",
+                    &[
+                        &session_id,
+                        &answer.question_id,
+                        &answer.choice_id,
+                        &answer.correct,
+                        &answer.answered_at,
+                        &answer.time_taken_ms,
+                    ],
+                )
+                .inspect_err(|e| {
+                    error!("Recording answer of session '{}' failed: {}", session_id, e);
+                    trans.set_rollback();
+                })?;
+
+            if updated_rows == 0 {
+                return Ok(false);
+            }
+
+            trans
+                .execute(
+                    "UPDATE quiz_sessions SET completed_at = $2 WHERE id = $1 AND NOT EXISTS \
+                     (SELECT 1 FROM quiz_session_answers WHERE session_id = $1 AND answered_at IS NULL)",
+                    &[&session_id, &answer.answered_at],
+                )
+                .inspect_err(|e| {
+                    error!("Completing session '{}' failed: {}", session_id, e);
+                    trans.set_rollback();
+                })?;
 
-        Ok(count)
+            trans.commit()?;
+            Ok(true)
+        })
+    }
+}
+
+pub struct LeaderboardsRepository {
+    pub connections: Rc<ConnectionManager>,
+}
+
+impl LeaderboardStore for LeaderboardsRepository {
+    fn record_score(&self, score: &Score) -> Result<(), RepositoryError> {
+        self.connections.with_connection(|conn| {
+            info!("record_score(score: '{:?}').", score);
+
+            conn.execute(
+                "INSERT INTO scores (session_id, player, category, score, total, completed_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (session_id) DO NOTHING",
+                &[
+                    &score.session_id,
+                    &score.player,
+                    &score.category,
+                    &score.score,
+                    &score.total,
+                    &score.completed_at,
+                ],
+            )?;
+            Ok(())
+        })
     }
 
-    pub fn get_questions(
+    fn count_players(
         &self,
         category: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<i64, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let count_rows = &conn.query(
+                "SELECT COUNT(DISTINCT player) FROM scores \
+                 WHERE category = $1 AND ($2::TIMESTAMPTZ IS NULL OR completed_at >= $2)",
+                &[&category, &since],
+            )?;
+
+            Ok(match count_rows.is_empty() {
+                true => 0i64,
+                false => count_rows.get(0).get(0),
+            })
+        })
+    }
+
+    fn get_leaderboard(
+        &self,
+        category: &str,
+        since: Option<DateTime<Utc>>,
         page: i64,
         size: i64,
-    ) -> Result<Vec<Question>, RepositoryError> {
-        let offset = match page {
-            0 => 0i64,
-            _ => (page - 1i64) * size,
-        };
+    ) -> Result<Vec<LeaderboardEntry>, RepositoryError> {
+        self.connections.with_read_connection(|conn| {
+            let offset = match page {
+                0 => 0i64,
+                _ => (page - 1i64) * size,
+            };
 
-        let question_rows = &self
-            .conn
-            .query(
-                "SELECT q.id,q.text FROM questions q INNER JOIN categories c ON c.name = q.category WHERE c.name = $1 AND c.active = TRUE LIMIT $2 OFFSET $3",
-                &[&category, &size, &offset],
-            )
-            .map_err(|e| {
-                error!("Error loading questions for category '{}': {}", category, e);
-                e
-            })?;
+            // DISTINCT ON keeps the first row of each player, i.e. their best and earliest score.
+            let rows = &conn
+                .query(
+                    "SELECT player, score, total, completed_at FROM ( \
+                         SELECT DISTINCT ON (player) player, score, total, completed_at FROM scores \
+                         WHERE category = $1 AND ($2::TIMESTAMPTZ IS NULL OR completed_at >= $2) \
+                         ORDER BY player, score DESC, completed_at ASC \
+                     ) best \
+                     ORDER BY score DESC, completed_at ASC, player \
+                     LIMIT $3 OFFSET $4",
+                    &[&category, &since, &size, &offset],
+                )
+                .inspect_err(|e| {
+                    error!("Error loading leaderboard for category '{}': {}", category, e)
+                })?;
 
-        if question_rows.is_empty() {
-            return Ok(vec![]);
-        }
+            Ok(rows
+                .iter()
+                .enumerate()
+                .map(|(index, row)| LeaderboardEntry {
+                    rank: offset + index as i64 + 1,
+                    player: row.get(0),
+                    score: row.get(1),
+                    total: row.get(2),
+                    completed_at: row.get(3),
+                })
+                .collect())
+        })
+    }
+}
 
-        let mut question_ids: Vec<i64> = vec![];
-        for question_row in question_rows {
-            let id: i64 = question_row.get(0);
-            question_ids.push(id);
-        }
+/**
+ * The conditions of `QuestionFilter`, shared by the count and the pages of questions.
+ *
+ * The parameters are the category or `NULL`, the difficulty levels, the tags and the number of
+ * them a question must have. A question's tags are unique, so counting the matching rows is
+ * enough to require all of them.
+ */
+const QUESTION_FILTER_CONDITIONS: &str = "c.active = TRUE AND ($1::VARCHAR IS NULL OR c.name = $1) AND q.difficulty BETWEEN $2 AND $3 AND ($5::BIGINT = 0 OR (SELECT COUNT(*) FROM question_tags t WHERE t.question_id = q.id AND t.tag = ANY($4::VARCHAR[])) >= $5)";
 
-        let choices_rows = &self
-            .conn
-            .query(
-                "SELECT id,text,correct,question_id FROM choices WHERE question_id = ANY($1)",
-                &[&question_ids],
-            )
-            .map_err(|e| {
-                error!(
-                    "Error loading choices for questions '{:?}': {}",
-                    question_ids, e
-                );
-                e
-            })?;
-
-        let mut choices_map: HashMap<i64, Vec<Choice>> = HashMap::new();
-        for choice_row in choices_rows {
-            let question_id: i64 = choice_row.get(3);
-            let choice = Choice {
-                id: choice_row.get(0),
-                title: choice_row.get(1),
-                correct: choice_row.get(2),
-            };
+/// The `questions.difficulty` column is constrained to the known levels.
+fn difficulty(level: i16) -> Difficulty {
+    Difficulty::from_level(level).unwrap_or_default()
+}
 
-            if let Some(mut choices) = choices_map.get_mut(&question_id) {
-                choices.push(choice);
-                continue;
-            }
+/// Loads the questions with the given ids and their choices, in the order of `ids`.
+fn load_questions(conn: &Connection, ids: &[i64]) -> Result<Vec<Question>, RepositoryError> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
 
-            choices_map.insert(question_id, vec![choice]);
-        }
+    let question_rows = &conn
+        .query(
+            "SELECT id,text,category,updated_at,difficulty FROM questions WHERE id = ANY($1)",
+            &[&ids],
+        )
+        .inspect_err(|e| error!("Error loading questions '{:?}': {}", ids, e))?;
 
-        let mut questions: Vec<Question> = Vec::with_capacity(question_rows.len());
-        for question_row in question_rows {
-            let id: i64 = question_row.get(0);
-            let text: String = question_row.get(1);
-            let choices: Vec<Choice> = choices_map.get(&id).unwrap_or(&vec![]).to_vec();
+    let mut choices_map = load_choices(conn, ids)?;
+    let mut tags_map = load_tags(conn, ids)?;
 
-            questions.push(Question {
+    let mut questions_map: HashMap<i64, Question> = HashMap::with_capacity(question_rows.len());
+    for question_row in question_rows {
+        let id: i64 = question_row.get(0);
+        questions_map.insert(
+            id,
+            Question {
                 id: Some(id),
-                question: text,
-                category: category.to_string(),
-                choices: choices,
-            });
+                question: question_row.get(1),
+                category: question_row.get(2),
+                choices: choices_map.remove(&id).unwrap_or_default(),
+                difficulty: difficulty(question_row.get(4)),
+                tags: tags_map.remove(&id).unwrap_or_default(),
+                updated_at: question_row.get(3),
+            },
+        );
+    }
+
+    Ok(ids
+        .iter()
+        .filter_map(|id| questions_map.remove(id))
+        .collect())
+}
+
+/// Loads the choices of the given questions, grouped by question id.
+fn load_choices(
+    conn: &Connection,
+    question_ids: &[i64],
+) -> Result<HashMap<i64, Vec<Choice>>, RepositoryError> {
+    let choices_rows = &conn
+        .query(
+            "SELECT id,text,correct,question_id FROM choices WHERE question_id = ANY($1) ORDER BY id",
+            &[&question_ids],
+        )
+        .map_err(|e| {
+            error!(
+                "Error loading choices for questions '{:?}': {}",
+                question_ids, e
+            );
+            e
+        })?;
+
+    let mut choices_map: HashMap<i64, Vec<Choice>> = HashMap::new();
+    for choice_row in choices_rows {
+        let question_id: i64 = choice_row.get(3);
+        let choice = Choice {
+            id: choice_row.get(0),
+            title: choice_row.get(1),
+            correct: choice_row.get(2),
+        };
+
+        if let Some(choices) = choices_map.get_mut(&question_id) {
+            choices.push(choice);
+            continue;
         }
 
-        Ok(questions)
+        choices_map.insert(question_id, vec![choice]);
+    }
+
+    Ok(choices_map)
+}
+
+/// Loads the tags of the given questions, grouped by question id.
+fn load_tags(
+    conn: &Connection,
+    question_ids: &[i64],
+) -> Result<HashMap<i64, Vec<String>>, RepositoryError> {
+    let tag_rows = &conn
+        .query(
+            "SELECT question_id,tag FROM question_tags WHERE question_id = ANY($1) ORDER BY tag",
+            &[&question_ids],
+        )
+        .inspect_err(|e| {
+            error!(
+                "Error loading tags for questions '{:?}': {}",
+                question_ids, e
+            )
+        })?;
+
+    let mut tags_map: HashMap<i64, Vec<String>> = HashMap::new();
+    for tag_row in tag_rows {
+        let question_id: i64 = tag_row.get(0);
+        tags_map
+            .entry(question_id)
+            .or_default()
+            .push(tag_row.get(1));
+    }
+
+    Ok(tags_map)
+}
+
+/// Inserts the normalized tags of a question, returning them.
+fn insert_tags(
+    trans: &Transaction,
+    question_id: i64,
+    tags: &[String],
+) -> Result<Vec<String>, RepositoryError> {
+    let tags = normalize_tags(tags);
+    if tags.is_empty() {
+        return Ok(tags);
+    }
+
+    trans
+        .execute(
+            "INSERT INTO question_tags (question_id, tag) SELECT $1, unnest($2::VARCHAR[])",
+            &[&question_id, &tags],
+        )
+        .inspect_err(|e| {
+            error!(
+                "Insert tags failed for question_id: '{}', reason: {}.",
+                question_id, e
+            );
+            trans.set_rollback();
+        })?;
+
+    Ok(tags)
+}
+
+/// Bulk inserts the choices of a question, returning them with their ids set.
+fn insert_choices(
+    trans: &Transaction,
+    question_id: i64,
+    choices: &[Choice],
+) -> Result<Vec<Choice>, RepositoryError> {
+    //A bulk insert needs at least one row. Questions are validated to have choices, but the store
+    //accepts questions without any, as the in-memory store does.
+    if choices.is_empty() {
+        return Ok(vec![]);
+    }
+
+    //Since we don't know how many choices a question has, we need to build a query string for bulk insert manually.
+
+    //value_placeholders refers to the `($1, $2)` part of the query.
+    let mut value_placeholders: Vec<String> = vec![];
+    //total is the number of fields to be inserted per choice multiplied by the number of choices
+    let num_fields = 3;
+    let total = num_fields * choices.len();
+
+    for i in (0..total).step_by(num_fields) {
+        value_placeholders.push(format!("(${}, ${}, ${})", i + 1, i + 2, i + 3))
     }
+
+    //join all the value placeholders i.e. ($1,$2), ($3,$4)
+    let joined_value_placeholders = value_placeholders.join(",");
+
+    let query_string = &format!(
+        "INSERT INTO choices (question_id, text, correct) VALUES {} RETURNING id",
+        joined_value_placeholders
+    );
+
+    let mut values: Vec<&dyn ToSql> = vec![];
+    for choice in choices.iter() {
+        values.push(&question_id);
+        values.push(&choice.title);
+        values.push(&choice.correct);
+    }
+
+    info!(
+        "Will insert choices for question id '{}' using query '{}' and values '{:?}'.",
+        question_id, query_string, values
+    );
+
+    let rows: Rows = trans
+        .query(query_string, values.as_slice())
+        .inspect_err(|e| {
+            error!(
+                "Bulk insert choices failed for question_id: '{}', reason: {}.",
+                question_id, e
+            );
+            //rollback will happen when transaction is dropped (i.e. Destructor)
+            trans.set_rollback();
+        })?;
+
+    // Create a new vector of choices, with the id field set.
+    let ids: Vec<i64> = rows.iter().map(|row| row.get(0)).collect();
+    let choices_with_ids: Vec<Choice> = choices
+        .iter()
+        .zip(ids.iter())
+        .map(|choice_id_tuple| {
+            let choice = choice_id_tuple.0;
+            let id = choice_id_tuple.1;
+            Choice {
+                id: Some(*id),
+                title: choice.title.clone(),
+                correct: choice.correct,
+            }
+        })
+        .collect();
+
+    Ok(choices_with_ids)
 }
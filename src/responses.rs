@@ -20,7 +20,7 @@ impl<T> PaginatedResponse<T> {
         total_records: u32,
         mut limit: u32,
     ) -> PaginatedResponse<T> {
-        if limit <= 0 {
+        if limit == 0 {
             limit = 1;
         }
 
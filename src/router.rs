@@ -0,0 +1,283 @@
+use apigateway::{APIErrorResponse, APIErrorType, APIGatewayEvent, APIGatewayResponse, Config};
+use auth::Permission;
+use log::info;
+use std::collections::HashMap;
+
+pub type Handler = fn(APIGatewayEvent, Config) -> Result<APIGatewayResponse, APIErrorResponse>;
+
+enum Segment {
+    Literal(String),
+    Parameter(String),
+}
+
+struct Route {
+    method: String,
+    template: String,
+    segments: Vec<Segment>,
+    handler: Handler,
+    permission: Option<Permission>,
+}
+
+impl Route {
+    /// Returns the path parameters if `path` matches the template of this route.
+    fn matches(&self, path: &[&str]) -> Option<HashMap<String, String>> {
+        if path.len() != self.segments.len() {
+            return None;
+        }
+
+        let mut parameters = HashMap::new();
+        for (segment, value) in self.segments.iter().zip(path.iter()) {
+            match segment {
+                Segment::Literal(literal) if literal == value => {}
+                Segment::Literal(_) => return None,
+                Segment::Parameter(name) => {
+                    parameters.insert(name.clone(), percent_decode(value));
+                }
+            }
+        }
+        Some(parameters)
+    }
+}
+
+/**
+ * Dispatches an `APIGatewayEvent` to a handler by HTTP method and path.
+ *
+ * Path templates use the API Gateway syntax, e.g. `/categories/{category}/active`.
+ * Parameters matched by the template are added to the event's `path_parameters` before
+ * the handler is called, so handlers can keep using `APIGatewayEvent::get_path_param`.
+ *
+ * Routes declare the permission their handler requires with `requires`. The router does not
+ * check it; `lambda_adapter` does, using `Router::permission`, and the `authorizer` lambda grants
+ * access to the routes that require one.
+ */
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    pub fn route(mut self, method: &str, template: &str, handler: Handler) -> Router {
+        let segments = split_path(template)
+            .into_iter()
+            .map(|segment| {
+                if segment.starts_with('{') && segment.ends_with('}') {
+                    Segment::Parameter(segment[1..segment.len() - 1].to_string())
+                } else {
+                    Segment::Literal(segment.to_string())
+                }
+            })
+            .collect();
+
+        self.routes.push(Route {
+            method: method.to_uppercase(),
+            template: template.to_string(),
+            segments,
+            handler,
+            permission: None,
+        });
+        self
+    }
+
+    /// Declares the permission required by the last added route.
+    pub fn requires(mut self, permission: Permission) -> Router {
+        if let Some(route) = self.routes.last_mut() {
+            route.permission = Some(permission);
+        }
+        self
+    }
+
+    /// The permission required by the route that `handle` would dispatch the event to.
+    pub fn permission(&self, event: &APIGatewayEvent) -> Option<Permission> {
+        let method = event.http_method.to_uppercase();
+        let segments = split_path(&event.path);
+
+        self.routes
+            .iter()
+            .find(|route| route.method == method && route.matches(&segments).is_some())
+            .and_then(|route| route.permission)
+    }
+
+    /// The method and path template of every route that requires a permission, in route order.
+    pub fn protected_routes(&self) -> Vec<(&str, &str)> {
+        self.routes
+            .iter()
+            .filter(|route| route.permission.is_some())
+            .map(|route| (route.method.as_str(), route.template.as_str()))
+            .collect()
+    }
+
+    pub fn handle(
+        &self,
+        mut event: APIGatewayEvent,
+        config: Config,
+    ) -> Result<APIGatewayResponse, APIErrorResponse> {
+        let method = event.http_method.to_uppercase();
+        let path = event.path.clone();
+        let segments = split_path(&path);
+
+        let mut path_matched = false;
+        for route in self.routes.iter() {
+            let parameters = match route.matches(&segments) {
+                Some(parameters) => parameters,
+                None => continue,
+            };
+            path_matched = true;
+
+            if route.method != method {
+                continue;
+            }
+
+            info!("Routing '{} {}' to '{}'.", method, path, route.template);
+
+            if !parameters.is_empty() {
+                event
+                    .path_parameters
+                    .get_or_insert_with(HashMap::new)
+                    .extend(parameters);
+            }
+            event.resource = Some(route.template.clone());
+            return (route.handler)(event, config);
+        }
+
+        Err(match path_matched {
+            true => APIErrorType::MethodNotAllowed { method, path },
+            false => APIErrorType::RouteNotFound { path },
+        }
+        .into())
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Decodes `%XX` escapes, leaving malformed escapes untouched.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+
+    let hex = |byte: u8| (byte as char).to_digit(16).map(|digit| digit as u8);
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push(high * 16 + low);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| value.to_string())
+}
+
+/// Decodes a query string, keeping every value of a repeated parameter in order.
+pub fn parse_query_string(query_string: &str) -> HashMap<String, Vec<String>> {
+    let mut query: HashMap<String, Vec<String>> = HashMap::new();
+    for pair in query_string.split('&').filter(|pair| !pair.is_empty()) {
+        let mut parts = pair.splitn(2, '=');
+        let name = parts.next().unwrap_or("").replace('+', " ");
+        let value = parts.next().unwrap_or("").replace('+', " ");
+        query
+            .entry(percent_decode(&name))
+            .or_default()
+            .push(percent_decode(&value));
+    }
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use handlers::in_memory_config;
+
+    fn echo_handler(
+        event: APIGatewayEvent,
+        _config: Config,
+    ) -> Result<APIGatewayResponse, APIErrorResponse> {
+        Ok(APIGatewayResponse::new(200, Some(&event.path_parameters)).unwrap())
+    }
+
+    fn router() -> Router {
+        Router::new()
+            .route("GET", "/categories", echo_handler)
+            .route("POST", "/categories/{category}/active", echo_handler)
+            .requires(Permission::CategoriesAdmin)
+    }
+
+    fn event(method: &str, path: &str) -> APIGatewayEvent {
+        APIGatewayEvent {
+            http_method: method.into(),
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_literal_path() {
+        let response = router()
+            .handle(event("GET", "/categories/"), in_memory_config())
+            .unwrap();
+        assert_eq!(response.status_code, 200);
+    }
+
+    #[test]
+    fn test_fills_path_parameters() {
+        let response = router()
+            .handle(
+                event("post", "/categories/General%20Knowledge/active"),
+                in_memory_config(),
+            )
+            .unwrap();
+
+        let parameters: Option<HashMap<String, String>> = response.parse().unwrap();
+        assert_eq!(
+            parameters.unwrap().get("category"),
+            Some(&"General Knowledge".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_path_returns_404() {
+        match router().handle(event("GET", "/questions"), in_memory_config()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), 404),
+        }
+    }
+
+    #[test]
+    fn test_unknown_method_returns_405() {
+        match router().handle(event("DELETE", "/categories"), in_memory_config()) {
+            Ok(resp) => panic!("Expected error, got {}", resp),
+            Err(err) => assert_eq!(err.status_code(), 405),
+        }
+    }
+
+    #[test]
+    fn test_permission_of_matched_route() {
+        let router = router();
+
+        assert_eq!(
+            router.permission(&event("POST", "/categories/Joke/active")),
+            Some(Permission::CategoriesAdmin)
+        );
+        assert_eq!(router.permission(&event("GET", "/categories")), None);
+        assert_eq!(router.permission(&event("GET", "/questions")), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b"), "a b");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/**
+ * A small seeded random number generator (SplitMix64).
+ *
+ * The sequence only depends on the seed, so a sample can be reproduced on any platform and
+ * after upgrades, which is not guaranteed by general purpose generators.
+ */
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed value in `0..bound`. `bound` must not be 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // Reject the values that would make the lower results more likely.
+        let zone = u64::MAX - (u64::MAX % bound);
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+/**
+ * A seed for callers that did not ask for a reproducible sample.
+ *
+ * Seeds are limited to 53 bits, so that they survive a round trip through JSON numbers in
+ * JavaScript and signed 64 bit integers in Java.
+ */
+pub fn random_seed() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    SeededRng::new(now.as_secs() ^ (u64::from(now.subsec_nanos()) << 32)).next_u64()
+        & ((1u64 << 53) - 1)
+}
+
+/**
+ * Returns `count` items picked at random without replacement, or all items (shuffled)
+ * if there are fewer than `count`.
+ *
+ * The same items, count and seed always produce the same sample.
+ */
+pub fn sample<T: Clone>(items: &[T], count: usize, seed: u64) -> Vec<T> {
+    let mut rng = SeededRng::new(seed);
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    let count = count.min(items.len());
+
+    // Partial Fisher-Yates: only the first `count` positions are shuffled.
+    for i in 0..count {
+        let j = i + rng.below((indices.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+
+    indices[..count]
+        .iter()
+        .map(|index| items[*index].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_gives_same_sample() {
+        let items: Vec<i64> = (1..=100).collect();
+
+        assert_eq!(sample(&items, 10, 42), sample(&items, 10, 42));
+        assert_ne!(sample(&items, 10, 42), sample(&items, 10, 43));
+    }
+
+    #[test]
+    fn test_sample_has_no_duplicates() {
+        let items: Vec<i64> = (1..=20).collect();
+
+        let mut sampled = sample(&items, 20, 7);
+        sampled.sort();
+        assert_eq!(sampled, items);
+    }
+
+    #[test]
+    fn test_count_larger_than_items() {
+        let items = vec!["a", "b"];
+
+        assert_eq!(sample(&items, 10, 1).len(), 2);
+        assert!(sample::<&str>(&[], 10, 1).is_empty());
+    }
+
+    #[test]
+    fn test_below_stays_in_bounds() {
+        let mut rng = SeededRng::new(3);
+        assert!((0..1000).all(|_| rng.below(6) < 6));
+    }
+}
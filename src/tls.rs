@@ -0,0 +1,448 @@
+use postgres::tls::{Stream, TlsHandshake, TlsStream};
+use repositories::RepositoryError;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore,
+    SignatureScheme,
+};
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/**
+ * How TLS is used for Postgres connections, following the libpq `sslmode` values.
+ *
+ * - `disable`: never use TLS.
+ * - `prefer`: use TLS if the server supports it, without verifying the certificate.
+ * - `require`: always use TLS, without verifying the certificate.
+ * - `verify-full`: always use TLS, verifying the certificate chain and the host name.
+ *
+ * As in libpq, `prefer` and `require` verify the certificate chain, but not the host name,
+ * when a root certificate is given (`verify-ca`).
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SslMode {
+    Disable,
+    Prefer,
+    Require,
+    VerifyFull,
+}
+
+impl FromStr for SslMode {
+    type Err = RepositoryError;
+
+    fn from_str(value: &str) -> Result<SslMode, RepositoryError> {
+        match value {
+            "disable" => Ok(SslMode::Disable),
+            "prefer" => Ok(SslMode::Prefer),
+            "require" => Ok(SslMode::Require),
+            "verify-full" => Ok(SslMode::VerifyFull),
+            _ => Err(RepositoryError::ConnectionError(format!(
+                "Invalid sslmode '{}'. Expected one of disable, prefer, require, verify-full",
+                value
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SslMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            SslMode::Disable => "disable",
+            SslMode::Prefer => "prefer",
+            SslMode::Require => "require",
+            SslMode::VerifyFull => "verify-full",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TlsSettings {
+    pub mode: SslMode,
+    /// Path to a PEM bundle of CA certificates used instead of the bundled web PKI roots.
+    pub root_cert: Option<String>,
+}
+
+impl TlsSettings {
+    /**
+     * Reads the TLS settings from the `sslmode` and `sslrootcert` connection string parameters,
+     * falling back to the `PGSSLMODE` and `PGSSLROOTCERT` environment variables.
+     * The mode defaults to `prefer`, like libpq.
+     *
+     * Returns the connection string without the TLS parameters, since Postgres would otherwise
+     * receive them as (unknown) run-time parameters.
+     */
+    pub fn from_conn_string(conn_string: &str) -> Result<(String, TlsSettings), RepositoryError> {
+        TlsSettings::parse(
+            conn_string,
+            env::var("PGSSLMODE").ok(),
+            env::var("PGSSLROOTCERT").ok(),
+        )
+    }
+
+    fn parse(
+        conn_string: &str,
+        default_mode: Option<String>,
+        default_root_cert: Option<String>,
+    ) -> Result<(String, TlsSettings), RepositoryError> {
+        let (base, query) = match conn_string.find('?') {
+            Some(index) => (&conn_string[..index], &conn_string[index + 1..]),
+            None => (conn_string, ""),
+        };
+
+        let mut mode = default_mode;
+        let mut root_cert = default_root_cert;
+        let mut remaining: Vec<&str> = vec![];
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("sslmode"), Some(value)) => mode = Some(value.to_string()),
+                (Some("sslrootcert"), Some(value)) => root_cert = Some(value.to_string()),
+                _ => remaining.push(pair),
+            }
+        }
+
+        let stripped = match remaining.is_empty() {
+            true => base.to_string(),
+            false => format!("{}?{}", base, remaining.join("&")),
+        };
+        let mode = match mode {
+            Some(mode) => mode.parse()?,
+            None => SslMode::Prefer,
+        };
+
+        Ok((stripped, TlsSettings { mode, root_cert }))
+    }
+}
+
+/// Performs Postgres TLS handshakes using rustls.
+#[derive(Debug)]
+pub struct RustlsHandshake {
+    config: Arc<ClientConfig>,
+}
+
+impl RustlsHandshake {
+    pub fn new(settings: &TlsSettings) -> Result<RustlsHandshake, RepositoryError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| RepositoryError::ConnectionError(format!("TLS setup failed: {}", e)))?;
+
+        let config = match (settings.mode, &settings.root_cert) {
+            (SslMode::VerifyFull, _) => builder
+                .with_root_certificates(root_certificates(settings)?)
+                .with_no_client_auth(),
+            (_, Some(_)) => {
+                let verifier = WebPkiServerVerifier::builder_with_provider(
+                    Arc::new(root_certificates(settings)?),
+                    provider,
+                )
+                .build()
+                .map_err(|e| {
+                    RepositoryError::ConnectionError(format!("TLS setup failed: {}", e))
+                })?;
+
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(CaVerification(verifier)))
+                    .with_no_client_auth()
+            }
+            (_, None) => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoCertificateVerification(provider)))
+                .with_no_client_auth(),
+        };
+
+        Ok(RustlsHandshake {
+            config: Arc::new(config),
+        })
+    }
+}
+
+fn root_certificates(settings: &TlsSettings) -> Result<RootCertStore, RepositoryError> {
+    let path = match settings.root_cert {
+        Some(ref path) => path,
+        None => {
+            return Ok(RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            })
+        }
+    };
+
+    let invalid_bundle = |message: String| {
+        RepositoryError::ConnectionError(format!("Invalid CA bundle at '{}': {}", path, message))
+    };
+
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(path).map_err(|e| invalid_bundle(e.to_string()))? {
+        let cert = cert.map_err(|e| invalid_bundle(e.to_string()))?;
+        roots.add(cert).map_err(|e| invalid_bundle(e.to_string()))?;
+    }
+
+    if roots.is_empty() {
+        return Err(invalid_bundle("no certificates found".into()));
+    }
+    Ok(roots)
+}
+
+impl TlsHandshake for RustlsHandshake {
+    fn tls_handshake(
+        &self,
+        host: &str,
+        mut stream: Stream,
+    ) -> Result<Box<dyn TlsStream>, Box<dyn Error + Sync + Send>> {
+        let server_name = ServerName::try_from(host.to_string())
+            .map_err(|e| format!("Invalid TLS server name '{}': {}", host, e))?;
+
+        let mut conn = ClientConnection::new(self.config.clone(), server_name)
+            .map_err(|e| format!("TLS handshake with '{}' failed: {}", host, e))?;
+
+        // Complete the handshake here, so that failures are reported while connecting.
+        while conn.is_handshaking() {
+            conn.complete_io(&mut stream)
+                .map_err(|e| format!("TLS handshake with '{}' failed: {}", host, e))?;
+        }
+
+        Ok(Box::new(RustlsStream(rustls::StreamOwned::new(
+            conn, stream,
+        ))))
+    }
+}
+
+struct RustlsStream(rustls::StreamOwned<ClientConnection, Stream>);
+
+impl fmt::Debug for RustlsStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RustlsStream").finish()
+    }
+}
+
+impl Read for RustlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for RustlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl TlsStream for RustlsStream {
+    fn get_ref(&self) -> &Stream {
+        self.0.get_ref()
+    }
+
+    fn get_mut(&mut self) -> &mut Stream {
+        self.0.get_mut()
+    }
+}
+
+/// Verifies the certificate chain but not the host name, for `prefer` and `require` with a root certificate.
+#[derive(Debug)]
+struct CaVerification(Arc<WebPkiServerVerifier>);
+
+impl ServerCertVerifier for CaVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer,
+        intermediates: &[CertificateDer],
+        server_name: &ServerName,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self
+            .0
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+        {
+            Err(rustls::Error::InvalidCertificate(CertificateError::NotValidForName)) => {
+                Ok(ServerCertVerified::assertion())
+            }
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Accepts any server certificate, for the `prefer` and `require` modes without a root certificate.
+/// Handshake signatures are still checked, so the connection is encrypted but not authenticated.
+#[derive(Debug)]
+struct NoCertificateVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer,
+        _intermediates: &[CertificateDer],
+        _server_name: &ServerName,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_defaults_to_prefer() {
+        let (conn_string, settings) =
+            TlsSettings::parse("postgres://user@localhost:5432/db", None, None).unwrap();
+
+        assert_eq!(conn_string, "postgres://user@localhost:5432/db");
+        assert_eq!(
+            settings,
+            TlsSettings {
+                mode: SslMode::Prefer,
+                root_cert: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_falls_back_to_environment_without_sslmode() {
+        let (conn_string, settings) = TlsSettings::parse(
+            "postgres://user@localhost/db?application_name=api",
+            Some("require".into()),
+            Some("/opt/ca.pem".into()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            conn_string,
+            "postgres://user@localhost/db?application_name=api"
+        );
+        assert_eq!(
+            settings,
+            TlsSettings {
+                mode: SslMode::Require,
+                root_cert: Some("/opt/ca.pem".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_strips_tls_parameters() {
+        let (conn_string, settings) = TlsSettings::parse(
+            "postgres://user@db.rds.amazonaws.com/db?sslmode=verify-full&application_name=api&sslrootcert=/opt/rds.pem",
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            conn_string,
+            "postgres://user@db.rds.amazonaws.com/db?application_name=api"
+        );
+        assert_eq!(settings.mode, SslMode::VerifyFull);
+        assert_eq!(settings.root_cert, Some("/opt/rds.pem".to_string()));
+    }
+
+    #[test]
+    fn test_parse_connection_string_overrides_environment() {
+        let (conn_string, settings) = TlsSettings::parse(
+            "postgres://user@localhost/db?sslmode=disable",
+            Some("require".into()),
+            Some("/opt/ca.pem".into()),
+        )
+        .unwrap();
+
+        assert_eq!(conn_string, "postgres://user@localhost/db");
+        assert_eq!(settings.mode, SslMode::Disable);
+        assert_eq!(settings.root_cert, Some("/opt/ca.pem".to_string()));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_mode() {
+        match TlsSettings::parse("postgres://localhost/db?sslmode=allow", None, None) {
+            Err(RepositoryError::ConnectionError(message)) => assert!(message.contains("allow")),
+            result => panic!("Expected ConnectionError, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_missing_ca_bundle_is_reported() {
+        for &mode in &[SslMode::Prefer, SslMode::Require, SslMode::VerifyFull] {
+            let settings = TlsSettings {
+                mode,
+                root_cert: Some("/nonexistent/ca.pem".into()),
+            };
+
+            match RustlsHandshake::new(&settings) {
+                Err(RepositoryError::ConnectionError(message)) => {
+                    assert!(message.contains("/nonexistent/ca.pem"))
+                }
+                result => panic!("Expected ConnectionError for {}, got {:?}", mode, result),
+            }
+        }
+    }
+}
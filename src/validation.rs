@@ -0,0 +1,452 @@
+use models::{Question, ValidationErrors};
+use std::env;
+
+/**
+ * The content rules of questions submitted by editors.
+ *
+ * Every rule reports a violation with a stable code and a pointer into the submitted question,
+ * and all violations are reported at once (see `ValidationErrors`). Lengths are counted in
+ * characters, after trimming whitespace.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionRules {
+    /// Requires exactly one correct choice, rather than at least one.
+    pub single_answer: bool,
+    pub min_choices: usize,
+    pub max_choices: usize,
+    pub max_question_length: usize,
+    pub max_choice_length: usize,
+    pub max_category_length: usize,
+    pub max_tags: usize,
+    pub max_tag_length: usize,
+}
+
+impl Default for QuestionRules {
+    fn default() -> QuestionRules {
+        QuestionRules {
+            single_answer: true,
+            min_choices: 2,
+            max_choices: 6,
+            max_question_length: 500,
+            max_choice_length: 200,
+            max_category_length: 50,
+            max_tags: 10,
+            max_tag_length: 30,
+        }
+    }
+}
+
+/// Reads the variable `name` through `lookup` as a `T`, or returns `default` if it is unset.
+fn var<T: ::std::str::FromStr>(
+    lookup: &dyn Fn(&str) -> Option<String>,
+    name: &str,
+    default: T,
+    expected: &str,
+) -> Result<T, String> {
+    match lookup(name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} must be {}", name, expected)),
+        None => Ok(default),
+    }
+}
+
+/// Category names start with a letter or digit, followed by letters, digits, spaces or `-&'.`
+fn is_category_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_alphanumeric)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == ' ' || "-&'.".contains(c))
+}
+
+/// Reports blank or overlong text at `pointer`, with codes prefixed by `code`.
+fn check_text(
+    errors: &mut ValidationErrors,
+    text: &str,
+    max_length: usize,
+    pointer: &str,
+    code: &str,
+) {
+    let length = text.trim().chars().count();
+    errors.check(
+        length > 0,
+        pointer,
+        &format!("{}.blank", code),
+        "Must not be blank",
+    );
+    errors.check(
+        length <= max_length,
+        pointer,
+        &format!("{}.too_long", code),
+        format!("Must be at most {} characters long", max_length),
+    );
+}
+
+impl QuestionRules {
+    /// Reports blank, overlong or malformed category names at `pointer`.
+    fn check_category(&self, errors: &mut ValidationErrors, name: &str, pointer: &str, code: &str) {
+        let name = name.trim();
+        check_text(errors, name, self.max_category_length, pointer, code);
+        errors.check(
+            name.is_empty() || is_category_name(name),
+            pointer,
+            &format!("{}.format", code),
+            "Must start with a letter or digit and contain only letters, digits, spaces and -&'.",
+        );
+    }
+
+    /**
+     * Reads `QUESTION_SINGLE_ANSWER` (`true`/`false`), `QUESTION_MIN_CHOICES`,
+     * `QUESTION_MAX_CHOICES`, `QUESTION_MAX_LENGTH`, `CHOICE_MAX_LENGTH`, `CATEGORY_MAX_LENGTH`,
+     * `QUESTION_MAX_TAGS` and `TAG_MAX_LENGTH`. Unset variables keep their default.
+     */
+    pub fn from_env() -> Result<QuestionRules, String> {
+        QuestionRules::from_lookup(&|name| env::var(name).ok())
+    }
+
+    /// Reads the rules from the variables returned by `lookup`, so they can be tested without
+    /// changing the environment of the test process.
+    fn from_lookup(lookup: &dyn Fn(&str) -> Option<String>) -> Result<QuestionRules, String> {
+        let defaults = QuestionRules::default();
+        let number = "a number";
+        let rules = QuestionRules {
+            single_answer: var(
+                lookup,
+                "QUESTION_SINGLE_ANSWER",
+                defaults.single_answer,
+                "true or false",
+            )?,
+            min_choices: var(lookup, "QUESTION_MIN_CHOICES", defaults.min_choices, number)?,
+            max_choices: var(lookup, "QUESTION_MAX_CHOICES", defaults.max_choices, number)?,
+            max_question_length: var(
+                lookup,
+                "QUESTION_MAX_LENGTH",
+                defaults.max_question_length,
+                number,
+            )?,
+            max_choice_length: var(
+                lookup,
+                "CHOICE_MAX_LENGTH",
+                defaults.max_choice_length,
+                number,
+            )?,
+            max_category_length: var(
+                lookup,
+                "CATEGORY_MAX_LENGTH",
+                defaults.max_category_length,
+                number,
+            )?,
+            max_tags: var(lookup, "QUESTION_MAX_TAGS", defaults.max_tags, number)?,
+            max_tag_length: var(lookup, "TAG_MAX_LENGTH", defaults.max_tag_length, number)?,
+        };
+
+        if rules.min_choices > rules.max_choices {
+            return Err(
+                "QUESTION_MIN_CHOICES must not be greater than QUESTION_MAX_CHOICES".to_string(),
+            );
+        }
+        Ok(rules)
+    }
+
+    /// Checks the title of a category created or renamed through `/categories`.
+    pub fn validate_category(&self, title: &str) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        self.check_category(&mut errors, title, "/title", "category.title");
+        errors.into_result()
+    }
+
+    pub fn validate(&self, question: &Question) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        check_text(
+            &mut errors,
+            &question.question,
+            self.max_question_length,
+            "/question",
+            "question.text",
+        );
+
+        self.check_category(
+            &mut errors,
+            &question.category,
+            "/category",
+            "question.category",
+        );
+
+        let choices = &question.choices;
+        errors.check(
+            choices.len() >= self.min_choices,
+            "/choices",
+            "question.choices.too_few",
+            format!("At least {} choices required", self.min_choices),
+        );
+        errors.check(
+            choices.len() <= self.max_choices,
+            "/choices",
+            "question.choices.too_many",
+            format!("At most {} choices allowed", self.max_choices),
+        );
+
+        let correct = choices.iter().filter(|choice| choice.correct).count();
+        errors.check(
+            correct > 0,
+            "/choices",
+            "question.choices.no_correct",
+            "A correct choice is required",
+        );
+        errors.check(
+            correct <= 1 || !self.single_answer,
+            "/choices",
+            "question.choices.multiple_correct",
+            "Only one correct choice allowed",
+        );
+
+        let mut titles: Vec<String> = Vec::with_capacity(choices.len());
+        for (index, choice) in choices.iter().enumerate() {
+            let pointer = format!("/choices/{}/title", index);
+            check_text(
+                &mut errors,
+                &choice.title,
+                self.max_choice_length,
+                &pointer,
+                "question.choice.title",
+            );
+
+            let title = choice.title.trim().to_lowercase();
+            errors.check(
+                title.is_empty() || !titles.contains(&title),
+                pointer,
+                "question.choices.duplicate",
+                format!("'{}' is already a choice", choice.title.trim()),
+            );
+            titles.push(title);
+        }
+
+        errors.check(
+            question.tags.len() <= self.max_tags,
+            "/tags",
+            "question.tags.too_many",
+            format!("At most {} tags allowed", self.max_tags),
+        );
+        for (index, tag) in question.tags.iter().enumerate() {
+            check_text(
+                &mut errors,
+                tag,
+                self.max_tag_length,
+                &format!("/tags/{}", index),
+                "question.tag",
+            );
+        }
+
+        errors.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{Choice, ValidationError};
+
+    fn question(text: &str, category: &str, choices: &[(&str, bool)]) -> Question {
+        Question {
+            id: None,
+            question: text.into(),
+            category: category.into(),
+            choices: choices
+                .iter()
+                .map(|(title, correct)| Choice {
+                    id: None,
+                    title: title.to_string(),
+                    correct: *correct,
+                })
+                .collect(),
+            difficulty: Default::default(),
+            tags: vec![],
+            updated_at: None,
+        }
+    }
+
+    fn codes(question: &Question, rules: &QuestionRules) -> Vec<(String, String)> {
+        match rules.validate(question) {
+            Ok(_) => vec![],
+            Err(errors) => errors
+                .errors
+                .into_iter()
+                .map(|error| {
+                    let ValidationError::Constraint { pointer, code, .. } = error;
+                    (pointer, code)
+                })
+                .collect(),
+        }
+    }
+
+    fn pair(pointer: &str, code: &str) -> (String, String) {
+        (pointer.to_string(), code.to_string())
+    }
+
+    #[test]
+    fn test_valid_question() {
+        let valid = question(
+            "Which planet is largest?",
+            "Space & Science",
+            &[("Jupiter", true), ("Mars", false)],
+        );
+        assert_eq!(codes(&valid, &QuestionRules::default()), vec![]);
+    }
+
+    #[test]
+    fn test_every_violation_is_reported() {
+        let invalid = question(
+            "  ",
+            "#space",
+            &[("Jupiter", false), (" jupiter ", false), ("", false)],
+        );
+
+        assert_eq!(
+            codes(&invalid, &QuestionRules::default()),
+            vec![
+                pair("/question", "question.text.blank"),
+                pair("/category", "question.category.format"),
+                pair("/choices", "question.choices.no_correct"),
+                pair("/choices/1/title", "question.choices.duplicate"),
+                pair("/choices/2/title", "question.choice.title.blank"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limits_are_configurable() {
+        let rules = QuestionRules {
+            single_answer: false,
+            max_choices: 3,
+            max_question_length: 10,
+            ..Default::default()
+        };
+        let long = question(
+            "Which of these are gas giants?",
+            "Space",
+            &[
+                ("Jupiter", true),
+                ("Saturn", true),
+                ("Mars", false),
+                ("Venus", false),
+            ],
+        );
+
+        assert_eq!(
+            codes(&long, &rules),
+            vec![
+                pair("/question", "question.text.too_long"),
+                pair("/choices", "question.choices.too_many"),
+            ]
+        );
+        assert_eq!(
+            codes(&long, &QuestionRules::default()),
+            vec![pair("/choices", "question.choices.multiple_correct")]
+        );
+
+        let single = question("Pick one", "Space", &[("Only", true)]);
+        assert_eq!(
+            codes(&single, &rules),
+            vec![pair("/choices", "question.choices.too_few")]
+        );
+
+        let rules = QuestionRules {
+            min_choices: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            codes(&question("Pick one", "Space", &[]), &rules),
+            vec![pair("/choices", "question.choices.no_correct")]
+        );
+    }
+
+    #[test]
+    fn test_category_titles() {
+        let rules = QuestionRules::default();
+        assert_eq!(rules.validate_category(" Space & Science "), Ok(()));
+
+        let codes = |title: &str| match rules.validate_category(title) {
+            Ok(_) => vec![],
+            Err(errors) => errors
+                .errors
+                .into_iter()
+                .map(|error| {
+                    let ValidationError::Constraint { pointer, code, .. } = error;
+                    (pointer, code)
+                })
+                .collect(),
+        };
+        assert_eq!(
+            codes("#space"),
+            vec![pair("/title", "category.title.format")]
+        );
+        assert_eq!(codes(" "), vec![pair("/title", "category.title.blank")]);
+        assert_eq!(
+            codes(&"a".repeat(51)),
+            vec![pair("/title", "category.title.too_long")]
+        );
+    }
+
+    #[test]
+    fn test_rules_from_variables() {
+        let rules = QuestionRules::from_lookup(&|name| match name {
+            "QUESTION_SINGLE_ANSWER" => Some("false".into()),
+            "QUESTION_MAX_CHOICES" => Some(" 4 ".into()),
+            "TAG_MAX_LENGTH" => Some("12".into()),
+            _ => None,
+        });
+        assert_eq!(
+            rules,
+            Ok(QuestionRules {
+                single_answer: false,
+                max_choices: 4,
+                max_tag_length: 12,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            QuestionRules::from_lookup(&|_| None),
+            Ok(QuestionRules::default())
+        );
+        assert_eq!(
+            QuestionRules::from_lookup(&|name| match name {
+                "QUESTION_MAX_LENGTH" => Some("long".into()),
+                _ => None,
+            }),
+            Err("QUESTION_MAX_LENGTH must be a number".to_string())
+        );
+        assert!(QuestionRules::from_lookup(&|name| match name {
+            "QUESTION_MIN_CHOICES" => Some("5".into()),
+            "QUESTION_MAX_CHOICES" => Some("3".into()),
+            _ => None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_tag_rules() {
+        let mut tagged = question("Pick one", "Space", &[("Only", true), ("Other", false)]);
+        tagged.tags = vec![
+            "planets".into(),
+            " ".into(),
+            "gas giants".into(),
+            "moons".into(),
+        ];
+        let rules = QuestionRules {
+            max_tags: 3,
+            max_tag_length: 8,
+            ..Default::default()
+        };
+        assert_eq!(
+            codes(&tagged, &rules),
+            vec![
+                pair("/tags", "question.tags.too_many"),
+                pair("/tags/1", "question.tag.blank"),
+                pair("/tags/2", "question.tag.too_long"),
+            ]
+        );
+    }
+}